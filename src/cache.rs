@@ -0,0 +1,194 @@
+use std::{collections::HashSet, fs, path::PathBuf, process, sync::Mutex};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+use crate::config::LinterConfig;
+
+/// Directory in the project root the analysis of each file is kept in between runs
+const CACHE_DIR: &str = ".css-linter-cache";
+
+/// Cached analysis of the file at `path`, valid while the key of the file matches
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    path: String,
+    key: u64,
+    data: T,
+}
+
+/// Per-file analysis results stored on disk, reused while the content of the file,
+/// the tool version and the config options that change the analysis stay the same.
+/// Unreadable entries are treated as missing and overwritten.
+pub struct Cache {
+    /// `None` when caching is turned off or the directory can't be created
+    dir: Option<PathBuf>,
+    fingerprint: String,
+    /// Entry files read or written by this run
+    touched: Mutex<HashSet<String>>,
+}
+
+impl Cache {
+    pub fn open(config: &LinterConfig) -> Self {
+        Self::open_in(PathBuf::from(CACHE_DIR), config)
+    }
+
+    fn open_in(dir: PathBuf, config: &LinterConfig) -> Self {
+        let is_ready = config.cache && fs::create_dir_all(&dir).is_ok();
+        if is_ready && !dir.join(".gitignore").exists() {
+            let _ = fs::write(dir.join(".gitignore"), "*\n");
+        }
+
+        Cache {
+            dir: is_ready.then_some(dir),
+            fingerprint: format!(
+                "{}:{}:{}",
+                env!("CARGO_PKG_VERSION"),
+                config.decorators,
+                config.class_helpers.join(",")
+            ),
+            touched: Mutex::default(),
+        }
+    }
+
+    /// Cache that neither reads nor stores anything, for text that is not saved to disk
+    pub fn disabled() -> Self {
+        Cache {
+            dir: None,
+            fingerprint: String::new(),
+            touched: Mutex::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    fn key(&self, code: &str) -> u64 {
+        let mut hasher = Xxh3::new();
+        hasher.update(self.fingerprint.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(code.as_bytes());
+        hasher.digest()
+    }
+
+    fn entry_path(&self, path: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let name = format!("{:016x}.json", xxh3_64(path.as_bytes()));
+        let entry_path = dir.join(&name);
+        self.touched.lock().unwrap().insert(name);
+        Some(entry_path)
+    }
+
+    /// Analysis of `path` stored while it held `code`
+    pub fn get<T: DeserializeOwned>(&self, path: &str, code: &str) -> Option<T> {
+        let contents = fs::read(self.entry_path(path)?).ok()?;
+        let entry: Entry<T> = serde_json::from_slice(&contents).ok()?;
+        (entry.path == path && entry.key == self.key(code)).then_some(entry.data)
+    }
+
+    /// Stores the analysis of `path` holding `code`, failures only cost a cache miss later
+    pub fn put<T: Serialize>(&self, path: &str, code: &str, data: &T) {
+        let Some(entry_path) = self.entry_path(path) else {
+            return;
+        };
+        let entry = Entry {
+            path: path.to_string(),
+            key: self.key(code),
+            data,
+        };
+        let Ok(contents) = serde_json::to_vec(&entry) else {
+            return;
+        };
+
+        // Written aside and renamed, so other runs never read a partial entry
+        let temp_path = entry_path.with_extension(format!("{}.tmp", process::id()));
+        if fs::write(&temp_path, contents).is_err() || fs::rename(&temp_path, entry_path).is_err() {
+            let _ = fs::remove_file(temp_path);
+        }
+    }
+
+    /// Removes the entries of files this run did not see, only a run that analyzed
+    /// every script and style module of the project may call it
+    pub fn prune(&self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        let touched = self.touched.lock().unwrap();
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".json") && !touched.contains(&name) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_files(dir: &std::path::Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect()
+    }
+
+    #[test]
+    fn unreadable_entries_are_misses_and_get_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::open_in(dir.path().to_path_buf(), &LinterConfig::default());
+        cache.put("a.tsx", "code", &vec![1, 2, 3]);
+        let [entry] = entry_files(dir.path()).try_into().unwrap();
+
+        let contents = fs::read(&entry).unwrap();
+        fs::write(&entry, &contents[..contents.len() / 2]).unwrap();
+        assert_eq!(cache.get::<Vec<i32>>("a.tsx", "code"), None);
+
+        fs::write(&entry, "not json").unwrap();
+        assert_eq!(cache.get::<Vec<i32>>("a.tsx", "code"), None);
+        // Data of another shape than the one asked for
+        cache.put("a.tsx", "code", &"text");
+        assert_eq!(cache.get::<Vec<i32>>("a.tsx", "code"), None);
+
+        cache.put("a.tsx", "code", &vec![1, 2, 3]);
+        assert_eq!(cache.get::<Vec<i32>>("a.tsx", "code"), Some(vec![1, 2, 3]));
+        assert_eq!(entry_files(dir.path()), [entry]);
+    }
+
+    #[test]
+    fn entries_only_match_the_same_code_and_fingerprint() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LinterConfig::default();
+        let cache = Cache::open_in(dir.path().to_path_buf(), &config);
+        cache.put("a.tsx", "code", &1);
+        assert_eq!(cache.get::<i32>("a.tsx", "code"), Some(1));
+        assert_eq!(cache.get::<i32>("a.tsx", "changed code"), None);
+
+        let decorators = LinterConfig {
+            decorators: !config.decorators,
+            ..Default::default()
+        };
+        let class_helpers = LinterConfig {
+            class_helpers: vec!["cx".into()],
+            ..Default::default()
+        };
+        for config in [decorators, class_helpers] {
+            let cache = Cache::open_in(dir.path().to_path_buf(), &config);
+            assert_eq!(cache.get::<i32>("a.tsx", "code"), None);
+        }
+
+        let mut other_version = Cache::open_in(dir.path().to_path_buf(), &config);
+        other_version.fingerprint =
+            other_version
+                .fingerprint
+                .replacen(env!("CARGO_PKG_VERSION"), "0.0.0", 1);
+        assert_eq!(other_version.get::<i32>("a.tsx", "code"), None);
+    }
+}
@@ -19,3 +19,51 @@ pub fn get_compiler_options() -> anyhow::Result<Properties> {
 
     Ok(serde_json::from_str(&tsconfig_contents)?)
 }
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+/// Optional `css-linter.json` in the project root, e.g. `{ "rules": { "unused-export": "off" } }`
+#[derive(Deserialize, Debug)]
+pub struct LinterConfig {
+    #[serde(default)]
+    pub rules: HashMap<String, RuleLevel>,
+    /// Whether scripts are parsed with decorator syntax
+    #[serde(default = "enabled")]
+    pub decorators: bool,
+    /// Names of `classnames/bind`-like helpers besides the imports of `classnames/bind`
+    #[serde(default, rename(deserialize = "classHelpers"))]
+    pub class_helpers: Vec<String>,
+    /// Whether analysis results are kept in `.css-linter-cache` between runs
+    #[serde(default = "enabled")]
+    pub cache: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for LinterConfig {
+    fn default() -> Self {
+        LinterConfig {
+            rules: HashMap::new(),
+            decorators: enabled(),
+            class_helpers: Vec::new(),
+            cache: enabled(),
+        }
+    }
+}
+
+pub fn get_linter_config() -> anyhow::Result<LinterConfig> {
+    match fs::read_to_string("css-linter.json") {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(LinterConfig::default()),
+        Err(e) => Err(e.into()),
+    }
+}
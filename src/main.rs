@@ -2,13 +2,15 @@ use std::env;
 
 use anyhow::Result;
 use modules::{
-    css_class::get_class_body, defined_classes::get_defined_classes, help::print_help, linter,
+    css_class::get_class_body, defined_classes::get_defined_classes, help::print_help, linter, lsp,
     styles_imports::get_styles_imports, used_css::get_class_usages, version::get_version,
 };
 
+mod cache;
 mod config;
 mod modules;
 mod parsers;
+mod style_flow;
 mod utils;
 
 fn main() -> Result<()> {
@@ -24,6 +26,7 @@ fn main() -> Result<()> {
         Some(arg) if arg == "--classes" => get_defined_classes()?,
         Some(arg) if arg == "--class" => get_class_body()?,
         Some(arg) if arg == "--usages" => get_class_usages()?,
+        Some(arg) if arg == "--lsp" => lsp::serve()?,
         Some(_) => print_help(),
         None => print_help(),
     };
@@ -1,19 +1,24 @@
 use std::{env, fs, process};
 
 use anyhow::Result;
-use regex::Regex;
+
+use crate::parsers::{
+    find_class_rule, find_icss_export, find_value_rule, parse_stylesheet, Dialect,
+};
 
 pub fn get_class_body() -> Result<()> {
     const COLOR_RED: &str = "\x1b[31m";
     const COLOR_RESET: &str = "\u{001B}[0m";
 
     let args: Vec<String> = env::args().collect();
-    let path = match args.get(2) {
-        Some(path) if !path.ends_with(".module.css") => {
-            eprintln!("{}Error{}: Invalid file extension.", COLOR_RED, COLOR_RESET);
-            process::exit(1);
-        }
-        Some(path) => path,
+    let (path, dialect) = match args.get(2) {
+        Some(path) => match Dialect::from_path(path) {
+            Some(dialect) => (path, dialect),
+            None => {
+                eprintln!("{}Error{}: Invalid file extension.", COLOR_RED, COLOR_RESET);
+                process::exit(1);
+            }
+        },
         None => {
             eprintln!("Path to the file must be provided");
             process::exit(1);
@@ -26,22 +31,22 @@ pub fn get_class_body() -> Result<()> {
     });
 
     let code = fs::read_to_string(path)?;
-    let pattern = format!(
-        r"\.{}[^A-Za-z0-9_-][^{{}}]*\{{[^}}]*\}}",
-        regex::escape(class_name)
-    );
-    let re = Regex::new(&pattern)?;
-
-    for m in re.find_iter(&code) {
-        if m.as_str().starts_with(&format!(".{}", class_name)) {
-            let trimmed = m.as_str().trim_start();
-
-            match trimmed {
-                s if s.starts_with('.') || s.ends_with('}') => print!("{}", trimmed),
-                _ => print!("    {}", trimmed),
-            }
-            break;
-        }
+    if let Some(body) = class_body(&code, dialect, class_name) {
+        print!("{}", body);
     }
     Ok(())
 }
+
+/// Source of the rule defining `class_name`, or of the `:export` declaration
+/// or `@value` rule with that name
+pub fn class_body<'a>(code: &'a str, dialect: Dialect, class_name: &str) -> Option<&'a str> {
+    let stylesheet = parse_stylesheet(code, dialect);
+
+    if let Some(rule) = find_class_rule(&stylesheet, class_name) {
+        Some(rule.span.slice(code))
+    } else if let Some(declaration) = find_icss_export(&stylesheet, class_name) {
+        Some(declaration.span.slice(code))
+    } else {
+        find_value_rule(&stylesheet, class_name).map(|rule| rule.span.slice(code))
+    }
+}
@@ -2,19 +2,21 @@ use std::{env, fs, process};
 
 use anyhow::Result;
 
-use crate::parsers::extract_classes;
+use crate::parsers::{analyze_css_module, Dialect};
 
 pub fn get_defined_classes() -> Result<()> {
     const COLOR_RED: &str = "\x1b[31m";
     const COLOR_RESET: &str = "\u{001B}[0m";
 
     let args: Vec<String> = env::args().collect();
-    let path = match args.get(2) {
-        Some(path) if !path.ends_with(".module.css") => {
-            eprintln!("{}Error{}: Invalid file extension.", COLOR_RED, COLOR_RESET);
-            process::exit(1);
-        }
-        Some(path) => path,
+    let (path, dialect) = match args.get(2) {
+        Some(path) => match Dialect::from_path(path) {
+            Some(dialect) => (path, dialect),
+            None => {
+                eprintln!("{}Error{}: Invalid file extension.", COLOR_RED, COLOR_RESET);
+                process::exit(1);
+            }
+        },
         None => {
             eprintln!("Path to the file must be provided");
             process::exit(1);
@@ -22,10 +24,12 @@ pub fn get_defined_classes() -> Result<()> {
     };
 
     let code = fs::read_to_string(path)?;
-    let imported_css = extract_classes(&code);
+    let module = analyze_css_module(&code, dialect);
 
-    imported_css
+    module
+        .classes
         .iter()
+        .filter(|class_name| !class_name.is_global)
         .try_for_each(|class_name| -> Result<()> {
             println!(
                 "{}:{}:{}",
@@ -33,5 +37,15 @@ pub fn get_defined_classes() -> Result<()> {
             );
             Ok(())
         })?;
+
+    for value in &module.values {
+        println!("{}:{}:{}", value.name, value.line_index, value.column_index);
+    }
+    for export in &module.icss_exports {
+        println!(
+            "{}:{}:{}",
+            export.name, export.line_index, export.column_index
+        );
+    }
     Ok(())
 }
@@ -3,10 +3,13 @@ pub fn print_help() {
         "Usage: css-linter [OPTION]\
         \n\nOptions:\
         \n  -v\t\t\t\t\t Print version information\
-        \n  --lint <project path>\t\t\t Lint CSS and TSX files\
+        \n  --lint <project path>\t\t\t Lint CSS modules and scripts\
+        \n    --jobs <count>\t\t\t Number of threads to lint with, all cores by default\
+        \n    --watch\t\t\t\t Lint again whenever a file changes\
         \n  --imports <file path>\t\t\t Get all CSS imports in file\
         \n  --classes <file path>\t\t\t Get all defined CSS classes\
         \n  --class <file path> <class name>\t Get CSS class body\
+        \n  --lsp\t\t\t\t\t Start a language server over stdio\
         "
     );
 }
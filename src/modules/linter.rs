@@ -6,13 +6,17 @@ use std::{
 };
 
 use anyhow::Result;
+use rayon::prelude::*;
 
 use crate::{
-    config::get_compiler_options,
+    cache::Cache,
+    config::{get_compiler_options, get_linter_config, LinterConfig, Properties, RuleLevel},
+    modules::watch::watch,
     parsers::{
-        extract_classes, extract_default_css_imports, extract_used_classes, ClassName,
-        UsedClassName,
+        analyze_css_module, Binding, ClassName, ComposesFrom, Composition, CssModule, CssValue,
+        Dialect, IcssExport, ScriptDialect, UsageKind, UsedClassName, ValueImport,
     },
+    style_flow::ScriptFiles,
     utils::{list_files_in_directory, process_relative_import, replace_aliases},
 };
 
@@ -27,10 +31,30 @@ pub fn lint() -> Result<()> {
         process::exit(1);
     });
 
-    let minify = match args.get(3) {
-        Some(v) if v == "--minify" => true,
-        _ => false,
-    };
+    let mut minify = false;
+    let mut is_watching = false;
+    let mut jobs = None;
+    let mut options = args.iter().skip(3);
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--minify" => minify = true,
+            "--watch" => is_watching = true,
+            "--jobs" => {
+                let count = options.next().and_then(|n| n.parse::<usize>().ok());
+                jobs = Some(count.filter(|n| *n > 0).unwrap_or_else(|| {
+                    eprintln!("Number of jobs must be a positive integer");
+                    process::exit(1);
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(jobs) = jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()?;
+    }
 
     if let Err(e) = env::set_current_dir(Path::new(path)) {
         eprintln!(
@@ -48,205 +72,1064 @@ pub fn lint() -> Result<()> {
         process::exit(1);
     });
 
-    let dir = list_files_in_directory(Path::new(".").to_path_buf(), tsconfig.exclude);
+    let config = get_linter_config().unwrap_or_else(|e| {
+        eprintln!(
+            "\n{}Error{}: Could not load css-linter.json ({})",
+            COLOR_RED, COLOR_RESET, e
+        );
+        process::exit(1);
+    });
 
-    let mut used_classnames: HashMap<String, HashSet<UsedClassName>> = Default::default();
-    let mut defined_classnames: HashMap<String, HashSet<ClassName>> = Default::default();
+    let dir = list_files_in_directory(Path::new(".").to_path_buf(), &tsconfig.exclude);
 
-    for entry in &dir {
-        let path = entry.replace("\\", "/");
+    let cache = Cache::open(&config);
+    let mut project = Project::load(&dir, &config, &cache)?;
+    let used_classnames = project.used_classes(&tsconfig, &config, &cache)?;
+    let diagnostics = project.diagnostics(&used_classnames, &tsconfig, &config)?;
+    cache.prune();
 
-        if path.ends_with(".tsx") || path.ends_with(".jsx") {
-            let code = fs::read_to_string(entry)?;
-            let imported_css = extract_default_css_imports(&code).unwrap_or_else(|e| {
-                eprintln!("Could not parse file: {}\n{}", entry, e);
-                process::exit(1);
-            });
+    if minify {
+        print_results_minified(&diagnostics);
+    } else {
+        print_results(&diagnostics);
+    }
+
+    if is_watching {
+        watch(project, diagnostics, tsconfig, config, cache, minify)?;
+    }
+
+    Ok(())
+}
 
-            for (mut style_path, class_names) in imported_css {
-                process_relative_import(Path::new(entry), &mut style_path)?;
-                replace_aliases(&mut style_path, tsconfig.compiler_options.paths.clone());
+fn load_css_module(entry: &str, path: &str, dialect: Dialect, cache: &Cache) -> Result<CssModule> {
+    let code = fs::read_to_string(entry)?;
+    Ok(analyze_css(path, &code, dialect, cache))
+}
+
+fn analyze_css(path: &str, code: &str, dialect: Dialect, cache: &Cache) -> CssModule {
+    cache.get(path, code).unwrap_or_else(|| {
+        let module = analyze_css_module(code, dialect);
+        cache.put(path, code, &module);
+        module
+    })
+}
 
-                let used_fields = extract_used_classes(&code, &class_names, path.clone())
-                    .unwrap_or_else(|e| {
-                        eprintln!("Could not parse file: {}\n{}", entry, e);
-                        process::exit(1);
-                    });
+/// Analysis of every style module and script of the project, kept between lint runs
+/// of watch mode so that only changed files are read again
+pub struct Project {
+    scripts: ScriptFiles,
+    /// Style modules by path with forward slashes, with the path as listed
+    css_modules: HashMap<String, (String, CssModule)>,
+}
+
+impl Project {
+    /// Reads and analyzes every file of `dir`, a syntax error in any script ends the process
+    pub fn load(dir: &[String], config: &LinterConfig, cache: &Cache) -> Result<Self> {
+        let (project, errors) = Self::load_valid(dir, config, cache)?;
+        if let Some((entry, e)) = errors.into_iter().next() {
+            eprintln!("Could not parse file: {}\n{}", entry, e);
+            process::exit(1);
+        }
+        Ok(project)
+    }
 
-                used_classnames
-                    .entry(style_path)
-                    .or_insert_with(HashSet::new)
-                    .extend(used_fields);
+    /// Reads and analyzes every file of `dir`, leaving out the scripts with syntax errors,
+    /// which are returned in listing order
+    pub fn load_valid(
+        dir: &[String],
+        config: &LinterConfig,
+        cache: &Cache,
+    ) -> Result<(Self, Vec<(String, anyhow::Error)>)> {
+        // Scripts and style modules are analyzed side by side, each of them across all cores
+        let (scripts, css_modules) = rayon::join(
+            || ScriptFiles::load_valid(dir, config, cache),
+            || {
+                dir.par_iter()
+                    .filter_map(|entry| {
+                        let path = entry.replace("\\", "/");
+                        let dialect = Dialect::from_path(&path)?;
+                        let module = load_css_module(entry, &path, dialect, cache);
+                        Some(module.map(|module| (path, (entry.clone(), module))))
+                    })
+                    .collect::<Result<HashMap<_, _>>>()
+            },
+        );
+
+        let (scripts, errors) = scripts?;
+        let project = Project {
+            scripts,
+            css_modules: css_modules?,
+        };
+        Ok((project, errors))
+    }
+
+    /// Reads the file at `entry` again, files that are neither style modules nor scripts are skipped
+    pub fn update(&mut self, entry: &str, config: &LinterConfig, cache: &Cache) -> Result<()> {
+        let path = entry.replace("\\", "/");
+        if Dialect::from_path(&path).is_none() && ScriptDialect::from_path(&path).is_none() {
+            return Ok(());
+        }
+
+        let code = fs::read_to_string(entry)?;
+        self.update_code(entry, code, config, cache)
+    }
+
+    /// Analyzes the file at `entry` again with `code` as its content, e.g. unsaved editor changes
+    pub fn update_code(
+        &mut self,
+        entry: &str,
+        code: String,
+        config: &LinterConfig,
+        cache: &Cache,
+    ) -> Result<()> {
+        let path = entry.replace("\\", "/");
+        match Dialect::from_path(&path) {
+            Some(dialect) => {
+                let module = analyze_css(&path, &code, dialect, cache);
+                self.css_modules.insert(path, (entry.to_string(), module));
+                Ok(())
             }
-        } else if path.ends_with(".module.css") {
-            let code = fs::read_to_string(entry)?;
-            let classes = extract_classes(&code);
-            defined_classnames
-                .entry(path)
-                .or_insert_with(HashSet::new)
-                .extend(classes);
+            None => self.scripts.update(entry, code, config, cache),
         }
     }
 
-    if minify {
-        print_results_minified(defined_classnames, used_classnames);
-    } else {
-        print_results(defined_classnames, used_classnames);
+    /// Analysis of the style module at `path`
+    pub fn css_module(&self, path: &str) -> Option<&CssModule> {
+        self.css_modules.get(path).map(|(_, module)| module)
     }
 
-    Ok(())
+    /// Style modules imported as a whole by the script at `path`, with the bindings holding them
+    pub fn style_objects(&self, path: &str) -> &[(String, Binding)] {
+        self.scripts.style_objects(path)
+    }
+
+    /// Forgets the file at `entry`, or every file under it when it was a directory
+    pub fn remove(&mut self, entry: &str) {
+        let path = entry.replace("\\", "/");
+        let dir = format!("{}/", path);
+        self.css_modules
+            .retain(|file, _| file != &path && !file.starts_with(&dir));
+        self.scripts.remove(entry);
+    }
+
+    /// Usages of every style module by the scripts and by other rules of the style modules,
+    /// keyed by style module path
+    pub fn used_classes(
+        &mut self,
+        tsconfig: &Properties,
+        config: &LinterConfig,
+        cache: &Cache,
+    ) -> Result<HashMap<String, HashSet<UsedClassName>>> {
+        let mut used_classnames = self.scripts.used_classes(tsconfig, config, cache)?;
+        for (path, (_, module)) in &self.css_modules {
+            used_classnames.entry(path.clone()).or_default().extend(
+                module
+                    .reused_classes
+                    .iter()
+                    .chain(&module.reused_values)
+                    .map(|class| UsedClassName {
+                        file_name: path.clone(),
+                        line: class.line_index + 1,
+                        column: class.column_index,
+                        end_line: class.line_index + 1,
+                        end_column: class.end_column_index,
+                        class_name: class.class_name.clone(),
+                        kind: UsageKind::Exact,
+                    }),
+            );
+        }
+        Ok(used_classnames)
+    }
+
+    /// Diagnostics of the whole project given its `used_classnames`, sorted
+    pub fn diagnostics(
+        &self,
+        used_classnames: &HashMap<String, HashSet<UsedClassName>>,
+        tsconfig: &Properties,
+        config: &LinterConfig,
+    ) -> Result<Vec<Diagnostic>> {
+        let mut modules = StyleModules::default();
+        for (path, (entry, module)) in &self.css_modules {
+            for composition in &module.compositions {
+                let mut composition = composition.clone();
+                if let ComposesFrom::File(ref mut composed_path) = composition.from {
+                    process_relative_import(Path::new(entry), composed_path)?;
+                    replace_aliases(composed_path, &tsconfig.compiler_options.paths);
+                }
+                modules
+                    .compositions
+                    .entry(path.clone())
+                    .or_default()
+                    .push(composition);
+            }
+
+            for import in &module.value_imports {
+                let mut import = import.clone();
+                process_relative_import(Path::new(entry), &mut import.from)?;
+                replace_aliases(&mut import.from, &tsconfig.compiler_options.paths);
+                modules
+                    .value_imports
+                    .entry(path.clone())
+                    .or_default()
+                    .push(import);
+            }
+
+            modules
+                .icss_exports
+                .entry(path.clone())
+                .or_default()
+                .extend(module.icss_exports.iter().cloned());
+            modules
+                .values
+                .entry(path.clone())
+                .or_default()
+                .extend(module.values.iter().cloned());
+            modules
+                .classes
+                .entry(path.clone())
+                .or_default()
+                .extend(module.classes.iter().cloned());
+        }
+
+        Ok(collect_diagnostics(&modules, used_classnames, config))
+    }
 }
 
-fn print_results(
-    defined_classnames: HashMap<String, HashSet<ClassName>>,
-    used_classnames: HashMap<String, HashSet<UsedClassName>>,
-) {
-    const COLOR_BLUE: &str = "\x1b[34m";
-    const COLOR_YELLOW: &str = "\x1b[33m";
-    const COLOR_GREEN: &str = "\x1b[32m";
-    const COLOR_RESET: &str = "\u{001B}[0m";
+/// Everything collected from the project's style modules, keyed by stylesheet path
+#[derive(Default)]
+struct StyleModules {
+    classes: HashMap<String, HashSet<ClassName>>,
+    compositions: HashMap<String, Vec<Composition>>,
+    icss_exports: HashMap<String, HashSet<IcssExport>>,
+    values: HashMap<String, HashSet<CssValue>>,
+    /// `@value` imports with their paths resolved like TSX imports
+    value_imports: HashMap<String, Vec<ValueImport>>,
+}
 
-    let mut files_count = 0;
-    let mut errors_count = 0;
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Copy, Debug)]
+pub enum DiagnosticKind {
+    UnusedClass,
+    UndefinedClass,
+    GlobalClassAccess,
+    UndefinedComposition,
+    UnusedExport,
+    UnusedValue,
+    UndefinedValue,
+    DynamicAccess,
+    EscapedStyles,
+}
 
-    for (css_file, mut classes_tsx) in defined_classnames.clone() {
-        if let Some(used_css) = used_classnames.get(&css_file) {
-            let used_css_flatten: Vec<String> =
-                used_css.iter().map(|v| v.class_name.clone()).collect();
-            classes_tsx.retain(|v| !used_css_flatten.contains(&v.class_name));
+impl DiagnosticKind {
+    /// Name used by `css-linter.json` and `css-lint-disable-rule` comments
+    pub fn rule_name(self) -> &'static str {
+        match self {
+            DiagnosticKind::UnusedClass => "unused-class",
+            DiagnosticKind::UndefinedClass => "undefined-class",
+            DiagnosticKind::GlobalClassAccess => "global-class-access",
+            DiagnosticKind::UndefinedComposition => "undefined-composition",
+            DiagnosticKind::UnusedExport => "unused-export",
+            DiagnosticKind::UnusedValue => "unused-value",
+            DiagnosticKind::UndefinedValue => "undefined-value",
+            DiagnosticKind::DynamicAccess => "dynamic-access",
+            DiagnosticKind::EscapedStyles => "escaped-styles",
         }
+    }
 
-        if classes_tsx.is_empty() {
-            continue;
+    fn default_severity(self) -> Severity {
+        match self {
+            DiagnosticKind::UndefinedComposition | DiagnosticKind::UndefinedValue => {
+                Severity::Error
+            }
+            DiagnosticKind::EscapedStyles => Severity::Info,
+            _ => Severity::Warning,
         }
+    }
 
-        files_count += 1;
-        errors_count += classes_tsx.len();
+    /// Severity configured for the rule, `None` when it is turned off
+    fn severity(self, config: &LinterConfig) -> Option<Severity> {
+        match config.rules.get(self.rule_name()) {
+            Some(RuleLevel::Off) => None,
+            Some(RuleLevel::Info) => Some(Severity::Info),
+            Some(RuleLevel::Warning) => Some(Severity::Warning),
+            Some(RuleLevel::Error) => Some(Severity::Error),
+            None => Some(self.default_severity()),
+        }
+    }
 
-        println!("{}{}{}", COLOR_BLUE, css_file, COLOR_RESET);
+    pub fn message(self, class_name: &str) -> String {
+        match self {
+            DiagnosticKind::UnusedClass => format!("Unused class `{}` found.", class_name),
+            DiagnosticKind::UndefinedClass => format!("Undefined class `{}` was used.", class_name),
+            DiagnosticKind::GlobalClassAccess => format!(
+                "Global class `{}` accessed through module object.",
+                class_name
+            ),
+            DiagnosticKind::UndefinedComposition => {
+                format!("Composed class `{}` is not defined.", class_name)
+            }
+            DiagnosticKind::UnusedExport => format!("Unused export `{}` found.", class_name),
+            DiagnosticKind::UnusedValue => format!("Unused value `{}` found.", class_name),
+            DiagnosticKind::UndefinedValue => {
+                format!("Imported value `{}` is not defined.", class_name)
+            }
+            DiagnosticKind::EscapedStyles => format!(
+                "Styles object `{}` escapes here, all of its classes are considered used.",
+                class_name
+            ),
+            DiagnosticKind::DynamicAccess => format!(
+                "Class accessed through `{}` could not be resolved.",
+                class_name
+            ),
+        }
+    }
 
-        for extra in classes_tsx {
-            println!(
-                "{}{}:{}  {}Warn{}: Unused class `{}` found.",
-                COLOR_YELLOW,
-                extra.line_index + 1,
-                extra.column_index + 1,
-                COLOR_YELLOW,
-                COLOR_RESET,
-                extra.class_name
-            );
+    fn short_message(self) -> &'static str {
+        match self {
+            DiagnosticKind::UnusedClass => "Unused class found.",
+            DiagnosticKind::UndefinedClass => "Undefined class was used.",
+            DiagnosticKind::GlobalClassAccess => "Global class accessed through module object.",
+            DiagnosticKind::UndefinedComposition => "Composed class is not defined.",
+            DiagnosticKind::UnusedExport => "Unused export found.",
+            DiagnosticKind::UnusedValue => "Unused value found.",
+            DiagnosticKind::UndefinedValue => "Imported value is not defined.",
+            DiagnosticKind::DynamicAccess => "Dynamic class access could not be resolved.",
+            DiagnosticKind::EscapedStyles => {
+                "Styles object escapes, all of its classes are considered used."
+            }
         }
-        println!();
     }
+}
 
-    let mut undefined_classes: HashMap<String, HashSet<UsedClassName>> = HashMap::new();
+/// Highlighted part of a file, lines are one-based, columns zero-based and the end is exclusive
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug, Clone, Copy)]
+pub struct Range {
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
 
-    for (tsx_file, mut classes) in used_classnames {
-        if let Some(defined_css) = defined_classnames.get(&tsx_file) {
-            let defined_css: HashSet<String> =
-                defined_css.iter().map(|v| v.class_name.clone()).collect();
-            classes.retain(|v| !defined_css.contains(v.class_name.as_str()));
+impl Range {
+    /// Range of `name` written on a single line starting at `column`
+    fn of_name(line: usize, column: usize, name: &str) -> Self {
+        Range {
+            line,
+            column,
+            end_line: line,
+            end_column: column + name.chars().count(),
         }
+    }
 
-        if classes.is_empty() {
-            continue;
+    /// Range of a class, value or composition as written in its stylesheet
+    fn of_class(class: &ClassName) -> Self {
+        Range {
+            line: class.line_index + 1,
+            column: class.column_index,
+            end_line: class.line_index + 1,
+            end_column: class.end_column_index,
         }
+    }
+}
 
-        files_count += 1;
-        errors_count += classes.len();
+/// Single lint warning
+#[derive(Eq, PartialEq, Ord, PartialOrd, Debug)]
+pub struct Diagnostic {
+    pub file_name: String,
+    pub range: Range,
+    pub class_name: String,
+    pub kind: DiagnosticKind,
+    pub severity: Severity,
+}
 
-        for extra in classes {
-            undefined_classes
-                .entry(extra.file_name.clone())
-                .or_insert_with(HashSet::new)
-                .insert(extra);
-        }
+/// Resolves the stylesheet a composition points to, `None` for `from global`
+fn composition_target<'a>(css_file: &'a str, composition: &'a Composition) -> Option<&'a str> {
+    match &composition.from {
+        ComposesFrom::Local => Some(css_file),
+        ComposesFrom::File(path) => Some(path),
+        ComposesFrom::Global => None,
     }
+}
 
-    for undefined in undefined_classes {
-        println!("{}{}{}", COLOR_BLUE, undefined.0, COLOR_RESET);
-        for extra in undefined.1 {
-            println!(
-                "{}{}:{}  {}Warn{}: Undefined class `{}` was used.",
-                COLOR_YELLOW,
-                extra.line,
-                extra.column + 1,
-                COLOR_YELLOW,
-                COLOR_RESET,
-                extra.class_name
-            );
+impl StyleModules {
+    /// Names readable from the module object: local classes, `:export` keys and `@value`s
+    fn members<'a>(&'a self, css_file: &str) -> impl Iterator<Item = &'a str> {
+        let classes = self.classes.get(css_file).into_iter().flatten();
+        let exports = self.icss_exports.get(css_file).into_iter().flatten();
+        let values = self.values.get(css_file).into_iter().flatten();
+
+        classes
+            .filter(|c| !c.is_global)
+            .map(|c| c.class_name.as_str())
+            .chain(exports.map(|e| e.name.as_str()))
+            .chain(values.map(|v| v.name.as_str()))
+    }
+}
+
+/// Class names used in each stylesheet, including the ones selected by a dynamic key
+/// pattern and the ones pulled in through `composes` by a used class
+fn propagate_compositions(
+    used_classnames: &HashMap<String, HashSet<UsedClassName>>,
+    modules: &StyleModules,
+) -> HashMap<String, HashSet<String>> {
+    let compositions = &modules.compositions;
+    let mut used: HashMap<String, HashSet<String>> = used_classnames
+        .iter()
+        .map(|(file, classes)| {
+            let names = classes
+                .iter()
+                .flat_map(|v| match v.kind {
+                    UsageKind::Exact => vec![v.class_name.clone()],
+                    _ => modules
+                        .members(file)
+                        .filter(|member| v.kind.matches(&v.class_name, member))
+                        .map(str::to_string)
+                        .collect(),
+                })
+                .collect();
+            (file.clone(), names)
+        })
+        .collect();
+
+    let mut queue: Vec<(String, String)> = used
+        .iter()
+        .flat_map(|(file, names)| names.iter().map(|name| (file.clone(), name.clone())))
+        .collect();
+
+    while let Some((file, class_name)) = queue.pop() {
+        for composition in compositions.get(&file).into_iter().flatten() {
+            if composition.class_name != class_name {
+                continue;
+            }
+            let Some(target) = composition_target(&file, composition) else {
+                continue;
+            };
+
+            let composed = &composition.composed.class_name;
+            if used
+                .entry(target.to_string())
+                .or_default()
+                .insert(composed.clone())
+            {
+                queue.push((target.to_string(), composed.clone()));
+            }
         }
-        println!();
     }
 
-    if errors_count == 0 {
-        println!("{}✔{} No CSS lint warnings found", COLOR_GREEN, COLOR_RESET);
-    } else {
-        println!(
-            "Found {}{} warnings{} in {} files",
-            COLOR_YELLOW, errors_count, COLOR_RESET, files_count
-        );
+    used
+}
+
+/// Whether any member of `target` satisfies `is_match`. Stylesheets outside
+/// of the analyzed tree can't be checked, so they only have to exist.
+fn target_defines<T>(
+    members: &HashMap<String, HashSet<T>>,
+    target: &str,
+    is_match: impl Fn(&T) -> bool,
+) -> bool {
+    match members.get(target) {
+        Some(members) => members.iter().any(is_match),
+        None => Path::new(target).exists(),
     }
 }
 
-fn print_results_minified(
-    defined_classnames: HashMap<String, HashSet<ClassName>>,
-    used_classnames: HashMap<String, HashSet<UsedClassName>>,
-) {
-    for (css_file, mut classes_tsx) in defined_classnames.clone() {
-        if let Some(used_css) = used_classnames.get(&css_file) {
-            let used_css_flatten: Vec<String> =
-                used_css.iter().map(|v| v.class_name.clone()).collect();
-            classes_tsx.retain(|v| !used_css_flatten.contains(&v.class_name));
+fn collect_diagnostics(
+    modules: &StyleModules,
+    used_classnames: &HashMap<String, HashSet<UsedClassName>>,
+    config: &LinterConfig,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut push = |file_name: &str, range, class_name: &str, kind: DiagnosticKind| {
+        if let Some(severity) = kind.severity(config) {
+            diagnostics.push(Diagnostic {
+                file_name: file_name.to_string(),
+                range,
+                class_name: class_name.to_string(),
+                kind,
+                severity,
+            });
         }
+    };
 
-        if classes_tsx.is_empty() {
-            continue;
+    let mut used_names = propagate_compositions(used_classnames, modules);
+    // An imported value is used by the importing module
+    for imports in modules.value_imports.values() {
+        for import in imports {
+            used_names
+                .entry(import.from.clone())
+                .or_default()
+                .insert(import.imported.class_name.clone());
         }
+    }
+
+    for (css_file, classes) in &modules.classes {
+        let used = used_names.get(css_file);
 
-        for extra in classes_tsx {
-            println!(
-                "{}:{}:{}:{}:\"{}\": Unused class found.",
+        // Global classes are not part of the module object, so they can't be "used" from TSX
+        for class in classes {
+            if class.is_global || used.is_some_and(|used| used.contains(&class.class_name)) {
+                continue;
+            }
+            push(
                 css_file,
-                extra.line_index + 1,
-                extra.column_index + 1,
-                extra.class_name.len(),
-                extra.class_name
+                Range::of_class(class),
+                &class.class_name,
+                DiagnosticKind::UnusedClass,
             );
         }
     }
 
-    let mut undefined_classes: HashMap<String, HashSet<UsedClassName>> = HashMap::new();
+    for (css_file, exports) in &modules.icss_exports {
+        let used = used_names.get(css_file);
+        for export in exports {
+            if export.is_disabled || used.is_some_and(|used| used.contains(&export.name)) {
+                continue;
+            }
+            push(
+                css_file,
+                Range::of_name(export.line_index + 1, export.column_index, &export.name),
+                &export.name,
+                DiagnosticKind::UnusedExport,
+            );
+        }
+    }
 
-    for (tsx_file, mut classes) in used_classnames {
-        if let Some(defined_css) = defined_classnames.get(&tsx_file) {
-            let defined_css: HashSet<String> =
-                defined_css.iter().map(|v| v.class_name.clone()).collect();
-            classes.retain(|v| !defined_css.contains(v.class_name.as_str()));
+    for (css_file, values) in &modules.values {
+        let used = used_names.get(css_file);
+        for value in values {
+            if value.is_disabled || used.is_some_and(|used| used.contains(&value.name)) {
+                continue;
+            }
+            push(
+                css_file,
+                Range::of_name(value.line_index + 1, value.column_index, &value.name),
+                &value.name,
+                DiagnosticKind::UnusedValue,
+            );
         }
+    }
+
+    for (css_file, compositions) in &modules.compositions {
+        for composition in compositions {
+            let Some(target) = composition_target(css_file, composition) else {
+                continue;
+            };
 
-        if classes.is_empty() {
-            continue;
+            let composed = &composition.composed;
+            let is_defined = target_defines(&modules.classes, target, |c| {
+                !c.is_global && c.class_name == composed.class_name
+            });
+            if !is_defined {
+                push(
+                    css_file,
+                    Range::of_class(composed),
+                    &composed.class_name,
+                    DiagnosticKind::UndefinedComposition,
+                );
+            }
         }
+    }
 
-        for extra in classes {
-            undefined_classes
-                .entry(extra.file_name.clone())
-                .or_insert_with(HashSet::new)
-                .insert(extra);
+    for (css_file, imports) in &modules.value_imports {
+        for import in imports {
+            let imported = &import.imported;
+            let is_defined = target_defines(&modules.values, &import.from, |v| {
+                v.name == imported.class_name
+            });
+            if !is_defined {
+                push(
+                    css_file,
+                    Range::of_class(imported),
+                    &imported.class_name,
+                    DiagnosticKind::UndefinedValue,
+                );
+            }
         }
     }
 
-    for undefined in undefined_classes {
-        for extra in undefined.1 {
-            println!(
-                "{}:{}:{}:{}:\"{}\": Undefined class was used.",
-                undefined.0,
-                extra.line,
-                extra.column + 1,
-                extra.class_name.len(),
-                extra.class_name
-            );
+    for (css_file, classes) in used_classnames {
+        let mut local = HashSet::new();
+        let mut global = HashSet::new();
+        for class in modules.classes.get(css_file).into_iter().flatten() {
+            if class.is_global {
+                global.insert(class.class_name.as_str());
+            } else {
+                local.insert(class.class_name.as_str());
+            }
         }
+        // `:export` keys and `@value`s are members of the module object just like local classes
+        for export in modules.icss_exports.get(css_file).into_iter().flatten() {
+            local.insert(export.name.as_str());
+        }
+        for value in modules.values.get(css_file).into_iter().flatten() {
+            local.insert(value.name.as_str());
+        }
+
+        for class in classes {
+            let name = class.class_name.as_str();
+            let kind = match class.kind {
+                UsageKind::Exact if local.contains(name) => continue,
+                UsageKind::Exact if global.contains(name) => DiagnosticKind::GlobalClassAccess,
+                UsageKind::Exact => DiagnosticKind::UndefinedClass,
+                UsageKind::Pattern(_) => {
+                    let mut members = modules.members(css_file);
+                    if members.any(|member| class.kind.matches(name, member)) {
+                        continue;
+                    }
+                    DiagnosticKind::UndefinedClass
+                }
+                UsageKind::Dynamic => DiagnosticKind::DynamicAccess,
+                UsageKind::Escape => DiagnosticKind::EscapedStyles,
+            };
+            let range = Range {
+                line: class.line,
+                column: class.column,
+                end_line: class.end_line,
+                end_column: class.end_column,
+            };
+            push(&class.file_name, range, &class.class_name, kind);
+        }
+    }
+
+    diagnostics.sort();
+    diagnostics
+}
+
+/// Diagnostics under the name of their file, `fixed` ones are labeled as resolved
+fn print_by_file(diagnostics: &[&Diagnostic], fixed: bool) {
+    const COLOR_RED: &str = "\x1b[31m";
+    const COLOR_BLUE: &str = "\x1b[34m";
+    const COLOR_YELLOW: &str = "\x1b[33m";
+    const COLOR_GREEN: &str = "\x1b[32m";
+    const COLOR_RESET: &str = "\u{001B}[0m";
+
+    let mut current_file = None;
+
+    for diagnostic in diagnostics {
+        if current_file != Some(&diagnostic.file_name) {
+            if current_file.is_some() {
+                println!();
+            }
+            current_file = Some(&diagnostic.file_name);
+            println!("{}{}{}", COLOR_BLUE, diagnostic.file_name, COLOR_RESET);
+        }
+
+        let (color, label) = match diagnostic.severity {
+            _ if fixed => (COLOR_GREEN, "Fixed"),
+            Severity::Error => (COLOR_RED, "Error"),
+            Severity::Warning => (COLOR_YELLOW, "Warn"),
+            Severity::Info => (COLOR_BLUE, "Info"),
+        };
+        println!(
+            "{}{}:{}  {}{}{}: {}",
+            COLOR_YELLOW,
+            diagnostic.range.line,
+            diagnostic.range.column,
+            color,
+            label,
+            COLOR_RESET,
+            diagnostic.kind.message(&diagnostic.class_name)
+        );
+    }
+}
+
+/// Number of diagnostics of each severity and of files with diagnostics
+fn print_summary(diagnostics: &[Diagnostic]) {
+    const COLOR_RED: &str = "\x1b[31m";
+    const COLOR_BLUE: &str = "\x1b[34m";
+    const COLOR_YELLOW: &str = "\x1b[33m";
+    const COLOR_GREEN: &str = "\x1b[32m";
+    const COLOR_RESET: &str = "\u{001B}[0m";
+
+    if diagnostics.is_empty() {
+        println!("{}✔{} No CSS lint warnings found", COLOR_GREEN, COLOR_RESET);
+        return;
+    }
+
+    let counts: Vec<String> = [
+        (Severity::Error, COLOR_RED, "errors"),
+        (Severity::Warning, COLOR_YELLOW, "warnings"),
+        (Severity::Info, COLOR_BLUE, "infos"),
+    ]
+    .into_iter()
+    .filter_map(|(severity, color, label)| {
+        let count = diagnostics
+            .iter()
+            .filter(|d| d.severity == severity)
+            .count();
+        (count > 0).then(|| format!("{}{} {}{}", color, count, label, COLOR_RESET))
+    })
+    .collect();
+    let files: HashSet<&str> = diagnostics.iter().map(|d| d.file_name.as_str()).collect();
+
+    println!();
+    println!("Found {} in {} files", counts.join(" and "), files.len());
+}
+
+fn print_results(diagnostics: &[Diagnostic]) {
+    print_by_file(&diagnostics.iter().collect::<Vec<_>>(), false);
+    print_summary(diagnostics);
+}
+
+/// Prints what changed between two lint runs of watch mode: the resolved diagnostics
+/// and the new ones, or the whole list again when it is minified
+pub fn print_changes(previous: &[Diagnostic], current: &[Diagnostic], minify: bool) {
+    if previous == current {
+        return;
+    }
+    if minify {
+        print_results_minified(current);
+        println!();
+        return;
+    }
+
+    let fixed: Vec<&Diagnostic> = previous
+        .iter()
+        .filter(|d| current.binary_search(d).is_err())
+        .collect();
+    let added: Vec<&Diagnostic> = current
+        .iter()
+        .filter(|d| previous.binary_search(d).is_err())
+        .collect();
+
+    println!();
+    print_by_file(&fixed, true);
+    if !fixed.is_empty() && !added.is_empty() {
+        println!();
+    }
+    print_by_file(&added, false);
+    print_summary(current);
+}
+
+/// One diagnostic per line: `file:line:column:end_line:end_column:severity:"class": message`
+fn print_results_minified(diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+        println!(
+            "{}:{}:{}:{}:{}:{}:\"{}\": {}",
+            diagnostic.file_name,
+            diagnostic.range.line,
+            diagnostic.range.column,
+            diagnostic.range.end_line,
+            diagnostic.range.end_column,
+            severity,
+            diagnostic.class_name,
+            diagnostic.kind.short_message()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::slice;
+
+    use super::*;
+
+    fn class_names(project: &Project, path: &str) -> Vec<String> {
+        let mut names: Vec<String> = project
+            .css_module(path)
+            .unwrap()
+            .classes
+            .iter()
+            .map(|class| class.class_name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn setup() -> (tempfile::TempDir, String, LinterConfig, Cache) {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = dir
+            .path()
+            .join("a.module.css")
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&entry, ".a {}").unwrap();
+        let config = LinterConfig {
+            cache: false,
+            ..Default::default()
+        };
+        let cache = Cache::open(&config);
+        (dir, entry, config, cache)
+    }
+
+    #[test]
+    fn update_reads_a_changed_module_again() {
+        let (_dir, entry, config, cache) = setup();
+        let mut project = Project::load(slice::from_ref(&entry), &config, &cache).unwrap();
+        let path = entry.replace("\\", "/");
+        assert_eq!(class_names(&project, &path), ["a"]);
+
+        fs::write(&entry, ".a {}\n.b {}").unwrap();
+        project.update(&entry, &config, &cache).unwrap();
+
+        assert_eq!(class_names(&project, &path), ["a", "b"]);
+    }
+
+    #[test]
+    fn deleted_module_fails_to_update_and_is_removed() {
+        let (_dir, entry, config, cache) = setup();
+        let mut project = Project::load(slice::from_ref(&entry), &config, &cache).unwrap();
+
+        fs::remove_file(&entry).unwrap();
+        let e = project.update(&entry, &config, &cache).unwrap_err();
+        assert!(e
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound));
+
+        project.remove(&entry);
+        assert!(project.css_module(&entry.replace("\\", "/")).is_none());
+    }
+
+    #[test]
+    fn renamed_module_moves_to_the_new_path() {
+        let (dir, entry, config, cache) = setup();
+        let mut project = Project::load(slice::from_ref(&entry), &config, &cache).unwrap();
+
+        let renamed = dir
+            .path()
+            .join("b.module.css")
+            .to_string_lossy()
+            .into_owned();
+        fs::rename(&entry, &renamed).unwrap();
+        project.remove(&entry);
+        project.update(&renamed, &config, &cache).unwrap();
+
+        assert!(project.css_module(&entry.replace("\\", "/")).is_none());
+        assert_eq!(class_names(&project, &renamed.replace("\\", "/")), ["a"]);
+    }
+
+    #[test]
+    fn update_skips_files_that_are_not_analyzed() {
+        let (dir, entry, config, cache) = setup();
+        let mut project = Project::load(&[entry], &config, &cache).unwrap();
+
+        let image = dir.path().join("img.png");
+        fs::write(&image, [0xff, 0xfe, 0x00]).unwrap();
+        project
+            .update(&image.to_string_lossy(), &config, &cache)
+            .unwrap();
+    }
+
+    #[test]
+    fn remove_forgets_every_module_under_a_directory() {
+        let (dir, entry, config, cache) = setup();
+        let nested = dir.path().join("ui");
+        fs::create_dir(&nested).unwrap();
+        let button = nested
+            .join("button.module.css")
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&button, ".button {}").unwrap();
+        let mut project = Project::load(&[entry.clone(), button.clone()], &config, &cache).unwrap();
+
+        project.remove(&nested.to_string_lossy());
+
+        assert!(project.css_module(&button.replace("\\", "/")).is_none());
+        assert!(project.css_module(&entry.replace("\\", "/")).is_some());
+    }
+
+    /// `(file, name, kind, line, column)` of the diagnostics of a project made of `files`
+    fn lint(files: &[(&str, &str)]) -> Vec<(String, String, DiagnosticKind, usize, usize)> {
+        let dir = tempfile::tempdir().unwrap();
+        let entries: Vec<String> = files
+            .iter()
+            .map(|(name, code)| {
+                let entry = dir.path().join(name).to_string_lossy().into_owned();
+                fs::write(&entry, code).unwrap();
+                entry
+            })
+            .collect();
+        let config = LinterConfig {
+            cache: false,
+            ..Default::default()
+        };
+        let cache = Cache::open(&config);
+        let tsconfig: Properties =
+            serde_json::from_str(r#"{ "compilerOptions": { "paths": {} }, "exclude": [] }"#)
+                .unwrap();
+
+        let mut project = Project::load(&entries, &config, &cache).unwrap();
+        let used = project.used_classes(&tsconfig, &config, &cache).unwrap();
+        let root = format!("{}/", dir.path().to_string_lossy().replace("\\", "/"));
+        project
+            .diagnostics(&used, &tsconfig, &config)
+            .unwrap()
+            .into_iter()
+            .map(|d| {
+                (
+                    d.file_name.replace("\\", "/").replace(&root, ""),
+                    d.class_name,
+                    d.kind,
+                    d.range.line,
+                    d.range.column,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compositions_of_used_classes_are_used() {
+        let diagnostics = lint(&[
+            (
+                "card.module.css",
+                ".base { color: red }\n.card { composes: base; }\n.unused { composes: base; }",
+            ),
+            (
+                "card.tsx",
+                "import styles from './card.module.css';\nexport const Card = () => <div className={styles.card} />;",
+            ),
+        ]);
+
+        assert_eq!(
+            diagnostics,
+            vec![(
+                "card.module.css".into(),
+                "unused".into(),
+                DiagnosticKind::UnusedClass,
+                3,
+                1
+            )]
+        );
+    }
+
+    #[test]
+    fn compositions_from_files_follow_the_composed_file() {
+        let diagnostics = lint(&[
+            (
+                "shared.module.css",
+                ".a { color: red }\n.b { color: blue }\n.c { color: green }",
+            ),
+            (
+                "card.module.css",
+                ".card { composes: a b missing from './shared.module.css'; }\n.title { composes: nope; }",
+            ),
+            (
+                "card.tsx",
+                "import styles from './card.module.css';\nexport const Card = () => <div className={styles.card + styles.title} />;",
+            ),
+        ]);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "card.module.css".into(),
+                    "missing".into(),
+                    DiagnosticKind::UndefinedComposition,
+                    1,
+                    22
+                ),
+                (
+                    "card.module.css".into(),
+                    "nope".into(),
+                    DiagnosticKind::UndefinedComposition,
+                    2,
+                    19
+                ),
+                (
+                    "shared.module.css".into(),
+                    "c".into(),
+                    DiagnosticKind::UnusedClass,
+                    3,
+                    1
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn exports_are_members_of_the_module_object() {
+        let diagnostics = lint(&[
+            (
+                "theme.module.css",
+                ":export {\n  primary: #0c77f8;\n  accent: #f80;\n}",
+            ),
+            (
+                "theme.tsx",
+                "import theme from './theme.module.css';\nexport const color = theme.primary + theme.missing;",
+            ),
+        ]);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "theme.module.css".into(),
+                    "accent".into(),
+                    DiagnosticKind::UnusedExport,
+                    3,
+                    2
+                ),
+                (
+                    "theme.tsx".into(),
+                    "missing".into(),
+                    DiagnosticKind::UndefinedClass,
+                    2,
+                    43
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn imported_values_are_used_and_must_be_defined() {
+        let diagnostics = lint(&[
+            (
+                "colors.module.css",
+                "@value primary: #0c77f8;\n@value accent: #f80;\n@value unused: #000;",
+            ),
+            (
+                "card.module.css",
+                "@value primary, accent as brand, missing from './colors.module.css';\n@value gap: 4px;\n@value spare: 2px;\n.card { color: brand; margin: gap }",
+            ),
+            (
+                "card.tsx",
+                "import styles from './card.module.css';\nexport const Card = () => <div className={styles.card} style={{ color: styles.primary }} />;",
+            ),
+        ]);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                (
+                    "card.module.css".into(),
+                    "missing".into(),
+                    DiagnosticKind::UnusedValue,
+                    1,
+                    33
+                ),
+                (
+                    "card.module.css".into(),
+                    "missing".into(),
+                    DiagnosticKind::UndefinedValue,
+                    1,
+                    33
+                ),
+                (
+                    "card.module.css".into(),
+                    "spare".into(),
+                    DiagnosticKind::UnusedValue,
+                    3,
+                    7
+                ),
+                (
+                    "colors.module.css".into(),
+                    "unused".into(),
+                    DiagnosticKind::UnusedValue,
+                    3,
+                    7
+                ),
+            ]
+        );
     }
 }
@@ -0,0 +1,947 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, RequestId, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidChangeWatchedFiles, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as _, PublishDiagnostics,
+    },
+    request::{
+        CodeActionRequest, Completion, GotoDefinition, HoverRequest, References,
+        RegisterCapability, Request as _,
+    },
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams,
+    CodeActionProviderCapability, CodeActionResponse, CompletionItem, CompletionItemKind,
+    CompletionOptions, CompletionParams, CompletionResponse, CompletionTextEdit,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWatchedFilesRegistrationOptions, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, FileChangeType, FileSystemWatcher, GlobPattern,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, InitializeParams, Location, MarkupContent, MarkupKind, NumberOrString,
+    OneOf, Position, PublishDiagnosticsParams, Range, ReferenceParams, Registration,
+    RegistrationParams, ServerCapabilities, TextDocumentPositionParams, TextDocumentSyncCapability,
+    TextDocumentSyncKind, TextEdit, Url, WorkspaceEdit,
+};
+
+use crate::{
+    cache::Cache,
+    config::{get_compiler_options, get_linter_config, LinterConfig, Properties},
+    modules::{
+        css_class::class_body,
+        linter::{Diagnostic, Project, Severity},
+        watch::{project_entry, update_entry, CONFIG_FILES},
+    },
+    parsers::{CssModule, Dialect, UsageKind, UsedClassName},
+    utils::list_files_in_directory,
+};
+
+/// Rules a `css-lint-disable-rule` comment above the reported line turns off
+const DISABLE_RULES: [&str; 3] = ["unused-class", "unused-export", "unused-value"];
+
+/// Source of the published diagnostics
+const SOURCE: &str = "css-linter";
+
+/// What a style module defines under a name
+#[derive(Clone, Copy)]
+enum Definition {
+    Class,
+    Value,
+    Export,
+}
+
+/// Names a style module defines as `(name, definition, line, column, end_column)`,
+/// lines are zero-based
+fn defined_names(
+    module: &CssModule,
+) -> impl Iterator<Item = (&str, Definition, usize, usize, usize)> {
+    let classes = module
+        .classes
+        .iter()
+        .filter(|class| !class.is_global)
+        .map(|class| {
+            (
+                class.class_name.as_str(),
+                Definition::Class,
+                class.line_index,
+                class.column_index,
+                class.end_column_index,
+            )
+        });
+    let values = module.values.iter().map(|value| {
+        let name = value.name.as_str();
+        let end_column = value.column_index + name.chars().count();
+        (
+            name,
+            Definition::Value,
+            value.line_index,
+            value.column_index,
+            end_column,
+        )
+    });
+    let exports = module.icss_exports.iter().map(|export| {
+        let name = export.name.as_str();
+        let end_column = export.column_index + name.chars().count();
+        (
+            name,
+            Definition::Export,
+            export.line_index,
+            export.column_index,
+            end_column,
+        )
+    });
+
+    classes.chain(values).chain(exports)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// LSP position of the character `column` on the zero-based `line` of `text`,
+/// LSP counts columns in UTF-16 code units
+fn to_position(text: &str, line: usize, column: usize) -> Position {
+    let character = match text.lines().nth(line) {
+        Some(line) => line.chars().take(column).map(char::len_utf16).sum(),
+        None => column,
+    };
+    Position::new(line as u32, character as u32)
+}
+
+/// Character column of an LSP `position` in `text`
+fn to_column(text: &str, position: Position) -> usize {
+    let Some(line) = text.lines().nth(position.line as usize) else {
+        return position.character as usize;
+    };
+    let mut units = 0;
+    line.chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= position.character as usize
+        })
+        .count()
+}
+
+/// Range of a usage, whose lines are one-based
+fn usage_range(text: &str, usage: &UsedClassName) -> Range {
+    Range::new(
+        to_position(text, usage.line.saturating_sub(1), usage.column),
+        to_position(text, usage.end_line.saturating_sub(1), usage.end_column),
+    )
+}
+
+fn lsp_diagnostic(text: &str, diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
+    let range = diagnostic.range;
+    lsp_types::Diagnostic {
+        range: Range::new(
+            to_position(text, range.line.saturating_sub(1), range.column),
+            to_position(text, range.end_line.saturating_sub(1), range.end_column),
+        ),
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Info => DiagnosticSeverity::INFORMATION,
+        }),
+        code: Some(NumberOrString::String(
+            diagnostic.kind.rule_name().to_string(),
+        )),
+        source: Some(SOURCE.to_string()),
+        message: diagnostic.kind.message(&diagnostic.class_name),
+        ..Default::default()
+    }
+}
+
+/// Language of the fenced code blocks showing rules of a style module
+fn language(dialect: Dialect) -> &'static str {
+    match dialect {
+        Dialect::Css => "css",
+        Dialect::Scss => "scss",
+        Dialect::Sass => "sass",
+        Dialect::Less => "less",
+    }
+}
+
+fn load_project(tsconfig: &Properties, config: &LinterConfig, cache: &Cache) -> Result<Project> {
+    let dir = list_files_in_directory(Path::new(".").to_path_buf(), &tsconfig.exclude);
+    // Files being edited are often broken for a moment, they join the project once they parse
+    let (project, errors) = Project::load_valid(&dir, config, cache)?;
+    for (entry, e) in errors {
+        eprintln!("Could not parse file: {}\n{}", entry, e);
+    }
+    Ok(project)
+}
+
+/// The language server: the analyzed project with the unsaved changes of the editor applied
+struct Server {
+    connection: Connection,
+    /// Workspace root, the current directory of the server
+    root: PathBuf,
+    tsconfig: Properties,
+    config: LinterConfig,
+    cache: Cache,
+    project: Project,
+    /// Text of the documents open in the editor, by path with forward slashes
+    documents: HashMap<String, String>,
+    used_classnames: HashMap<String, HashSet<UsedClassName>>,
+    /// Diagnostics last published for each file
+    published: HashMap<String, Vec<Diagnostic>>,
+    /// Files changed since the diagnostics were published
+    is_stale: bool,
+}
+
+impl Server {
+    /// Current text of the file at `path`, the editor's when it is open
+    fn text(&self, path: &str) -> Option<Cow<'_, str>> {
+        match self.documents.get(path) {
+            Some(text) => Some(Cow::Borrowed(text)),
+            None => fs::read_to_string(path).ok().map(Cow::Owned),
+        }
+    }
+
+    fn uri(&self, path: &str) -> Option<Url> {
+        Url::from_file_path(self.root.join(path)).ok()
+    }
+
+    /// Path of a project file the way the project walk lists it
+    fn entry(&self, uri: &Url) -> Option<String> {
+        let file = uri.to_file_path().ok()?;
+        if !file.starts_with(&self.root) {
+            return None;
+        }
+        project_entry(&self.root, &file, &self.tsconfig.exclude)
+    }
+
+    /// Path with forward slashes of the document and the position in it
+    fn document_position(&self, params: &TextDocumentPositionParams) -> Option<(String, Position)> {
+        let entry = self.entry(&params.text_document.uri)?;
+        Some((entry.replace("\\", "/"), params.position))
+    }
+
+    fn send(&self, message: impl Into<Message>) -> Result<()> {
+        self.connection.sender.send(message.into())?;
+        Ok(())
+    }
+
+    /// Asks the editor to report changes of project files made outside of it
+    fn watch_files(&self) -> Result<()> {
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![FileSystemWatcher {
+                glob_pattern: GlobPattern::String("**/*".to_string()),
+                kind: None,
+            }],
+        };
+        let params = RegistrationParams {
+            registrations: vec![Registration {
+                id: "css-linter-watched-files".to_string(),
+                method: DidChangeWatchedFiles::METHOD.to_string(),
+                register_options: Some(serde_json::to_value(options)?),
+            }],
+        };
+        let id = RequestId::from("css-linter-watched-files".to_string());
+        self.send(Request::new(
+            id,
+            RegisterCapability::METHOD.to_string(),
+            params,
+        ))
+    }
+
+    fn run(&mut self) -> Result<()> {
+        loop {
+            // Diagnostics are published once every queued change is applied
+            let message = match self.connection.receiver.try_recv() {
+                Ok(message) => message,
+                Err(e) if e.is_disconnected() => return Ok(()),
+                Err(_) => {
+                    self.refresh()?;
+                    match self.connection.receiver.recv() {
+                        Ok(message) => message,
+                        Err(_) => return Ok(()),
+                    }
+                }
+            };
+
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.refresh()?;
+                    let response = self.handle_request(request);
+                    self.send(response)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Self::definition),
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Self::hover),
+            Completion::METHOD => self.respond::<Completion>(request, Self::completion),
+            References::METHOD => self.respond::<References>(request, Self::references),
+            CodeActionRequest::METHOD => {
+                self.respond::<CodeActionRequest>(request, Self::code_actions)
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request {}", method),
+            ),
+        }
+    }
+
+    /// Answers `request` with `handler`, which gets the params of a request of type `R`
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(e) => Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string()),
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.edit(&params.text_document.uri, params.text_document.text);
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Documents are synced in full, the last change holds the whole text
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.edit(&params.text_document.uri, change.text);
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let Some(entry) = self.entry(&params.text_document.uri) else {
+                    return Ok(());
+                };
+                // Unsaved changes are dropped with the document
+                self.documents.remove(&entry.replace("\\", "/"));
+                update_entry(&mut self.project, &entry, &self.config, &self.cache);
+                self.is_stale = true;
+            }
+            DidChangeWatchedFiles::METHOD => {
+                let params: DidChangeWatchedFilesParams =
+                    serde_json::from_value(notification.params)?;
+                self.apply_file_changes(params)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Analyzes the document at `uri` again with its text in the editor
+    fn edit(&mut self, uri: &Url, text: String) {
+        let Some(entry) = self.entry(uri) else {
+            return;
+        };
+        self.documents
+            .insert(entry.replace("\\", "/"), text.clone());
+        // While a script has a syntax error its last valid analysis is kept,
+        // unsaved text changes with every keystroke and stays out of the cache
+        if self
+            .project
+            .update_code(&entry, text, &self.config, &Cache::disabled())
+            .is_ok()
+        {
+            self.is_stale = true;
+        }
+    }
+
+    /// Reads the files changed outside of the editor again, open documents keep their editor text
+    fn apply_file_changes(&mut self, params: DidChangeWatchedFilesParams) -> Result<()> {
+        let mut changed = BTreeSet::new();
+        for change in params.changes {
+            if let Some(entry) = self.entry(&change.uri) {
+                changed.insert((entry, change.typ == FileChangeType::DELETED));
+            }
+        }
+
+        let is_config_changed = changed.iter().any(|(entry, _)| {
+            CONFIG_FILES
+                .iter()
+                .any(|file| *entry == format!("./{}", file))
+        });
+        if is_config_changed {
+            return self.reload();
+        }
+
+        for (entry, is_deleted) in changed {
+            let path = Path::new(&entry);
+            if self.documents.contains_key(&entry.replace("\\", "/")) {
+                continue;
+            }
+            if is_deleted || !path.exists() {
+                self.project.remove(&entry);
+            } else if path.is_dir() {
+                for file in list_files_in_directory(path.to_path_buf(), &self.tsconfig.exclude) {
+                    update_entry(&mut self.project, &file, &self.config, &self.cache);
+                }
+            } else {
+                update_entry(&mut self.project, &entry, &self.config, &self.cache);
+            }
+            self.is_stale = true;
+        }
+        Ok(())
+    }
+
+    /// Loads the configuration and the whole project again, keeping the editor text of open documents
+    fn reload(&mut self) -> Result<()> {
+        match (get_compiler_options(), get_linter_config()) {
+            (Ok(tsconfig), Ok(config)) => {
+                self.tsconfig = tsconfig;
+                self.config = config;
+            }
+            (Err(e), _) => {
+                eprintln!("Could not load tsconfig.json ({})", e);
+                return Ok(());
+            }
+            (_, Err(e)) => {
+                eprintln!("Could not load css-linter.json ({})", e);
+                return Ok(());
+            }
+        }
+
+        self.cache = Cache::open(&self.config);
+        self.project = load_project(&self.tsconfig, &self.config, &self.cache)?;
+        for (path, text) in &self.documents {
+            let _ = self
+                .project
+                .update_code(path, text.clone(), &self.config, &Cache::disabled());
+        }
+        self.is_stale = true;
+        Ok(())
+    }
+
+    /// Lints the project again after changes and publishes the diagnostics of every file
+    /// whose diagnostics changed
+    fn refresh(&mut self) -> Result<()> {
+        if !self.is_stale {
+            return Ok(());
+        }
+        self.is_stale = false;
+
+        let linted = self
+            .project
+            .used_classes(&self.tsconfig, &self.config, &self.cache)
+            .and_then(|used_classnames| {
+                let diagnostics =
+                    self.project
+                        .diagnostics(&used_classnames, &self.tsconfig, &self.config)?;
+                Ok((used_classnames, diagnostics))
+            });
+        let (used_classnames, diagnostics) = match linted {
+            Ok(linted) => linted,
+            Err(e) => {
+                eprintln!("Could not lint the project: {}", e);
+                return Ok(());
+            }
+        };
+        self.used_classnames = used_classnames;
+
+        let mut current: HashMap<String, Vec<Diagnostic>> = HashMap::new();
+        for diagnostic in diagnostics {
+            current
+                .entry(diagnostic.file_name.clone())
+                .or_default()
+                .push(diagnostic);
+        }
+
+        let files: BTreeSet<&String> = self.published.keys().chain(current.keys()).collect();
+        for path in files {
+            let diagnostics = current.get(path).map_or(&[][..], Vec::as_slice);
+            let previous = self.published.get(path).map_or(&[][..], Vec::as_slice);
+            if diagnostics == previous {
+                continue;
+            }
+            let Some(uri) = self.uri(path) else {
+                continue;
+            };
+            let text = self.text(path).unwrap_or_default();
+            let diagnostics = diagnostics
+                .iter()
+                .map(|diagnostic| lsp_diagnostic(&text, diagnostic))
+                .collect();
+            let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+            self.send(Notification::new(
+                PublishDiagnostics::METHOD.to_string(),
+                params,
+            ))?;
+        }
+        self.published = current;
+        Ok(())
+    }
+
+    /// Style module path and name of the class, value or export at `position` in the file
+    /// at `path`: a usage of it, or its definition in a style module
+    fn name_at(&self, path: &str, position: Position) -> Option<(String, String)> {
+        let text = self.text(path)?;
+        let line = position.line as usize;
+        let column = to_column(&text, position);
+
+        if let Some(module) = self.project.css_module(path) {
+            let defined = defined_names(module).find(|(_, _, name_line, start, end)| {
+                *name_line == line && (*start..=*end).contains(&column)
+            });
+            if let Some((name, ..)) = defined {
+                return Some((path.to_string(), name.to_string()));
+            }
+        }
+
+        self.used_classnames.iter().find_map(|(style_path, used)| {
+            used.iter()
+                .find(|usage| {
+                    usage.kind == UsageKind::Exact
+                        && usage.file_name == path
+                        && (usage.line, usage.column) <= (line + 1, column)
+                        && (line + 1, column) <= (usage.end_line, usage.end_column)
+                })
+                .map(|usage| (style_path.clone(), usage.class_name.clone()))
+        })
+    }
+
+    /// Locations defining `name` in the style module at `style_path`
+    fn definitions(&self, style_path: &str, name: &str) -> Vec<Location> {
+        let (Some(module), Some(uri)) = (self.project.css_module(style_path), self.uri(style_path))
+        else {
+            return Vec::new();
+        };
+        let text = self.text(style_path).unwrap_or_default();
+
+        let mut defined: Vec<(usize, usize, usize)> = defined_names(module)
+            .filter(|(defined, ..)| *defined == name)
+            .map(|(_, _, line, column, end_column)| (line, column, end_column))
+            .collect();
+        defined.sort();
+        defined
+            .into_iter()
+            .map(|(line, column, end_column)| {
+                let range = Range::new(
+                    to_position(&text, line, column),
+                    to_position(&text, line, end_column),
+                );
+                Location::new(uri.clone(), range)
+            })
+            .collect()
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let (path, position) = self.document_position(&params.text_document_position_params)?;
+        let (style_path, name) = self.name_at(&path, position)?;
+        Some(GotoDefinitionResponse::Array(
+            self.definitions(&style_path, &name),
+        ))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (path, position) = self.document_position(&params.text_document_position_params)?;
+        let (style_path, name) = self.name_at(&path, position)?;
+        let dialect = Dialect::from_path(&style_path)?;
+        let code = self.text(&style_path)?;
+        let body = class_body(&code, dialect, &name)?;
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```{}\n{}\n```", language(dialect), body),
+            }),
+            range: None,
+        })
+    }
+
+    /// Classes, values and exports of the style module imported as the object before the dot
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let (path, position) = self.document_position(&params.text_document_position)?;
+        let text = self.text(&path)?;
+        let line = text.lines().nth(position.line as usize)?;
+        let column = to_column(&text, position);
+
+        let prefix: String = line.chars().take(column).collect();
+        let typed = prefix.trim_end_matches(is_identifier_char);
+        let object = typed.strip_suffix('.')?;
+        let variable = &object[object.trim_end_matches(is_identifier_char).len()..];
+        let (style_path, _) = self
+            .project
+            .style_objects(&path)
+            .iter()
+            .find(|(_, binding)| binding.name == variable)?;
+        let module = self.project.css_module(style_path)?;
+
+        // Names that are not identifiers are accessed with brackets in place of the dot
+        let dot = to_position(&text, position.line as usize, object.chars().count());
+        let mut names: Vec<(&str, Definition)> = defined_names(module)
+            .map(|(name, definition, ..)| (name, definition))
+            .collect();
+        names.sort_by_key(|(name, _)| *name);
+        names.dedup_by_key(|(name, _)| *name);
+
+        let items = names
+            .into_iter()
+            .map(|(name, definition)| {
+                let detail = match definition {
+                    Definition::Class => format!(".{}", name),
+                    Definition::Value => format!("@value {}", name),
+                    Definition::Export => format!(":export {}", name),
+                };
+                let is_identifier = !name.starts_with(|c: char| c.is_ascii_digit())
+                    && name.chars().all(is_identifier_char);
+                let text_edit = (!is_identifier).then(|| {
+                    CompletionTextEdit::Edit(TextEdit::new(
+                        Range::new(dot, position),
+                        format!("['{}']", name),
+                    ))
+                });
+
+                CompletionItem {
+                    label: name.to_string(),
+                    kind: Some(CompletionItemKind::FIELD),
+                    detail: Some(detail),
+                    filter_text: text_edit.as_ref().map(|_| format!(".{}", name)),
+                    text_edit,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+
+    /// Usages of the class, value or export at the position, across the project
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let (path, position) = self.document_position(&params.text_document_position)?;
+        let (style_path, name) = self.name_at(&path, position)?;
+
+        let mut usages: Vec<&UsedClassName> = self
+            .used_classnames
+            .get(&style_path)?
+            .iter()
+            .filter(|usage| usage.kind == UsageKind::Exact && usage.class_name == name)
+            .collect();
+        usages.sort_by_key(|usage| (&usage.file_name, usage.line, usage.column));
+
+        let mut locations = if params.context.include_declaration {
+            self.definitions(&style_path, &name)
+        } else {
+            Vec::new()
+        };
+        let mut texts: HashMap<&str, Cow<'_, str>> = HashMap::new();
+        for usage in usages {
+            let Some(uri) = self.uri(&usage.file_name) else {
+                continue;
+            };
+            let text = texts
+                .entry(&usage.file_name)
+                .or_insert_with(|| self.text(&usage.file_name).unwrap_or_default());
+            locations.push(Location::new(uri, usage_range(text, usage)));
+        }
+        Some(locations)
+    }
+
+    /// Quick fixes adding a `css-lint-disable-rule` comment above the reported line
+    fn code_actions(&self, params: CodeActionParams) -> Option<CodeActionResponse> {
+        let entry = self.entry(&params.text_document.uri)?;
+        let text = self.text(&entry.replace("\\", "/"))?;
+
+        let actions = params
+            .context
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.source.as_deref() == Some(SOURCE))
+            .filter_map(|diagnostic| {
+                let Some(NumberOrString::String(rule)) = &diagnostic.code else {
+                    return None;
+                };
+                if !DISABLE_RULES.contains(&rule.as_str()) {
+                    return None;
+                }
+
+                let line = diagnostic.range.start.line;
+                let indent: String = text
+                    .lines()
+                    .nth(line as usize)
+                    .unwrap_or_default()
+                    .chars()
+                    .take_while(|c| c.is_whitespace())
+                    .collect();
+                let comment = format!("/* css-lint-disable-rule {} */", rule);
+                let start = Position::new(line, 0);
+                let edit =
+                    TextEdit::new(Range::new(start, start), format!("{}{}\n", indent, comment));
+
+                Some(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: format!("Add {}", comment),
+                    kind: Some(CodeActionKind::QUICKFIX),
+                    diagnostics: Some(vec![diagnostic.clone()]),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(HashMap::from([(
+                            params.text_document.uri.clone(),
+                            vec![edit],
+                        )])),
+                        ..Default::default()
+                    }),
+                    is_preferred: Some(true),
+                    ..Default::default()
+                }))
+            })
+            .collect();
+        Some(actions)
+    }
+}
+
+fn server_capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        references_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![".".to_string()]),
+            ..Default::default()
+        }),
+        code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+        ..Default::default()
+    }
+}
+
+/// Directory of the first workspace folder, or of the root URI for clients that only send that
+fn workspace_root(params: &InitializeParams) -> Option<PathBuf> {
+    #[allow(deprecated)]
+    let root_uri = params.root_uri.as_ref();
+    params
+        .workspace_folders
+        .as_ref()
+        .and_then(|folders| folders.first())
+        .map(|folder| &folder.uri)
+        .or(root_uri)
+        .and_then(|uri| uri.to_file_path().ok())
+}
+
+/// Runs a language server over stdio for the workspace the editor opens, linting
+/// the unsaved text of open documents as it changes
+pub fn serve() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = serde_json::to_value(server_capabilities())?;
+    let params: InitializeParams = serde_json::from_value(connection.initialize(capabilities)?)?;
+
+    if let Some(workspace) = workspace_root(&params) {
+        env::set_current_dir(workspace)?;
+    }
+
+    let tsconfig = get_compiler_options().unwrap_or_else(|e| {
+        eprintln!("Could not load tsconfig.json ({})", e);
+        process::exit(1);
+    });
+    let config = get_linter_config().unwrap_or_else(|e| {
+        eprintln!("Could not load css-linter.json ({})", e);
+        process::exit(1);
+    });
+    let cache = Cache::open(&config);
+    let project = load_project(&tsconfig, &config, &cache)?;
+
+    let watches_files = params
+        .capabilities
+        .workspace
+        .and_then(|workspace| workspace.did_change_watched_files)
+        .and_then(|watched_files| watched_files.dynamic_registration)
+        == Some(true);
+
+    let mut server = Server {
+        connection,
+        root: env::current_dir()?,
+        tsconfig,
+        config,
+        cache,
+        project,
+        documents: HashMap::new(),
+        used_classnames: HashMap::new(),
+        published: HashMap::new(),
+        is_stale: true,
+    };
+    if watches_files {
+        server.watch_files()?;
+    }
+    server.run()?;
+
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lsp_types::{CodeActionContext, ReferenceContext, TextDocumentIdentifier, WorkspaceFolder};
+
+    use super::*;
+
+    const CSS: &str = ".card { color: red }\n  .unused { color: blue }\n";
+    /// The emoji takes two UTF-16 code units but a single character
+    const TSX: &str =
+        "import styles from './a.module.css';\nconst e = '😀'; const c = styles.card;\n";
+
+    fn server() -> (Server, Connection) {
+        let (connection, client) = Connection::memory();
+        let config = LinterConfig {
+            cache: false,
+            ..Default::default()
+        };
+        let cache = Cache::open(&config);
+        let project = Project::load_valid(&[], &config, &cache).unwrap().0;
+        let mut server = Server {
+            connection,
+            root: PathBuf::from("/project"),
+            tsconfig: serde_json::from_str(
+                r#"{ "compilerOptions": { "paths": {} }, "exclude": [] }"#,
+            )
+            .unwrap(),
+            config,
+            cache,
+            project,
+            documents: HashMap::new(),
+            used_classnames: HashMap::new(),
+            published: HashMap::new(),
+            is_stale: true,
+        };
+        server.edit(&uri("src/a.module.css"), CSS.to_string());
+        server.edit(&uri("src/a.tsx"), TSX.to_string());
+        server.refresh().unwrap();
+        (server, client)
+    }
+
+    fn uri(path: &str) -> Url {
+        Url::from_file_path(Path::new("/project").join(path)).unwrap()
+    }
+
+    fn position_params(path: &str, line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri(path)),
+            Position::new(line, character),
+        )
+    }
+
+    /// UTF-16 column of `styles.card` in the script
+    fn usage_start() -> u32 {
+        "const e = '😀'; const c = styles.".encode_utf16().count() as u32
+    }
+
+    #[test]
+    fn definition_of_a_usage_is_the_class_name() {
+        let (server, _client) = server();
+        let params = GotoDefinitionParams {
+            text_document_position_params: position_params("src/a.tsx", 1, usage_start() + 2),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let Some(GotoDefinitionResponse::Array(locations)) = server.definition(params) else {
+            panic!("no definition");
+        };
+        assert_eq!(
+            locations,
+            [Location::new(
+                uri("src/a.module.css"),
+                Range::new(Position::new(0, 1), Position::new(0, 5))
+            )]
+        );
+    }
+
+    #[test]
+    fn hover_shows_the_rule() {
+        let (server, _client) = server();
+        let params = HoverParams {
+            text_document_position_params: position_params("src/a.tsx", 1, usage_start()),
+            work_done_progress_params: Default::default(),
+        };
+
+        let HoverContents::Markup(markup) = server.hover(params).unwrap().contents else {
+            panic!("hover is not markup");
+        };
+        assert_eq!(markup.value, "```css\n.card { color: red }\n```");
+    }
+
+    #[test]
+    fn references_are_ranged_in_utf16() {
+        let (server, _client) = server();
+        let params = ReferenceParams {
+            text_document_position: position_params("src/a.module.css", 0, 3),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+            context: ReferenceContext {
+                include_declaration: false,
+            },
+        };
+
+        let start = usage_start();
+        assert_eq!(
+            server.references(params).unwrap(),
+            [Location::new(
+                uri("src/a.tsx"),
+                Range::new(Position::new(1, start), Position::new(1, start + 4))
+            )]
+        );
+    }
+
+    #[test]
+    fn code_action_inserts_an_indented_disable_comment() {
+        let (server, _client) = server();
+        let diagnostic = server.published["./src/a.module.css"]
+            .iter()
+            .map(|diagnostic| lsp_diagnostic(CSS, diagnostic))
+            .find(|diagnostic| diagnostic.range.start.line == 1)
+            .unwrap();
+        let params = CodeActionParams {
+            text_document: TextDocumentIdentifier::new(uri("src/a.module.css")),
+            range: diagnostic.range,
+            context: CodeActionContext {
+                diagnostics: vec![diagnostic],
+                only: None,
+                trigger_kind: None,
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        let actions = server.code_actions(params).unwrap();
+        let [CodeActionOrCommand::CodeAction(action)] = &actions[..] else {
+            panic!("{:?}", actions);
+        };
+        let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+        assert_eq!(
+            changes[&uri("src/a.module.css")],
+            [TextEdit::new(
+                Range::new(Position::new(1, 0), Position::new(1, 0)),
+                "  /* css-lint-disable-rule unused-class */\n".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn workspace_root_falls_back_to_the_root_uri() {
+        #[allow(deprecated)]
+        let mut params = InitializeParams {
+            root_uri: Some(uri("")),
+            ..Default::default()
+        };
+        assert_eq!(workspace_root(&params), Some(PathBuf::from("/project")));
+
+        params.workspace_folders = Some(vec![WorkspaceFolder {
+            uri: Url::from_file_path("/other").unwrap(),
+            name: "other".into(),
+        }]);
+        assert_eq!(workspace_root(&params), Some(PathBuf::from("/other")));
+    }
+}
@@ -4,4 +4,6 @@ pub mod linter;
 pub mod styles_imports;
 pub mod version;
 pub mod help;
-pub mod used_css;
\ No newline at end of file
+pub mod used_css;
+pub mod watch;
+pub mod lsp;
\ No newline at end of file
@@ -5,23 +5,40 @@ use serde_json::json;
 use std::collections::HashMap;
 
 use crate::{
-    config::get_compiler_options,
-    parsers::extract_default_css_imports,
+    cache::Cache,
+    config::{get_compiler_options, get_linter_config},
+    parsers::{ParsedScript, ScriptDialect},
+    style_flow::ScriptAnalysis,
     utils::{process_relative_import, replace_aliases},
 };
 
+/// `file` the way the project walk lists it, so that every command shares its cache entry
+fn project_path(file: &str) -> Result<String> {
+    let path = Path::new(file);
+    let cwd = env::current_dir()?;
+    let relative = match path.strip_prefix(&cwd) {
+        Ok(relative) => relative,
+        Err(_) if path.is_absolute() => return Ok(file.replace("\\", "/")),
+        Err(_) => path,
+    };
+    let relative = relative.to_string_lossy().replace("\\", "/");
+    Ok(format!("./{}", relative.trim_start_matches("./")))
+}
+
 pub fn get_styles_imports() -> Result<()> {
     const COLOR_RED: &str = "\x1b[31m";
     const COLOR_RESET: &str = "\u{001B}[0m";
 
     let args: Vec<String> = env::args().collect();
 
-    let path = match args.get(2) {
-        Some(path) if !path.ends_with(".tsx") && !path.ends_with(".jsx") => {
-            eprintln!("{}Error{}: Invalid file extension.", COLOR_RED, COLOR_RESET);
-            process::exit(1);
-        }
-        Some(path) => path,
+    let (path, dialect) = match args.get(2) {
+        Some(path) => match ScriptDialect::from_path(path) {
+            Some(dialect) => (path, dialect),
+            None => {
+                eprintln!("{}Error{}: Invalid file extension.", COLOR_RED, COLOR_RESET);
+                process::exit(1);
+            }
+        },
         None => {
             eprintln!("Path to the file must be provided");
             process::exit(1);
@@ -36,19 +53,40 @@ pub fn get_styles_imports() -> Result<()> {
         process::exit(1);
     });
 
+    let config = get_linter_config().unwrap_or_else(|e| {
+        eprintln!(
+            "\n{}Error{}: Could not load css-linter.json ({})",
+            COLOR_RED, COLOR_RESET, e
+        );
+        process::exit(1);
+    });
+
     let code = fs::read_to_string(path)?;
-    let imported_css = extract_default_css_imports(&code)?;
+    let cache = Cache::open(&config);
+    let project_path = project_path(path)?;
+    let analysis = match cache.get::<ScriptAnalysis>(&project_path, &code) {
+        Some(analysis) => analysis,
+        None => {
+            let syntax = dialect.syntax(config.decorators);
+            let script = ParsedScript::parse(&code, project_path.clone(), syntax)?;
+            let analysis = ScriptAnalysis::new(&script);
+            cache.put(&project_path, &code, &analysis);
+            analysis
+        }
+    };
+    let imported_css = analysis.css_imports;
 
     let mut imports_map = HashMap::new();
 
     imported_css
+        .objects
         .iter()
         .try_for_each(|(style_path, class_name)| -> Result<()> {
             let mut style_path = style_path.clone();
             process_relative_import(Path::new(path), &mut style_path)?;
-            replace_aliases(&mut style_path, tsconfig.compiler_options.paths.clone());
+            replace_aliases(&mut style_path, &tsconfig.compiler_options.paths);
 
-            imports_map.insert(style_path, class_name.clone());
+            imports_map.insert(style_path, class_name.name.clone());
             Ok(())
         })?;
 
@@ -1,11 +1,13 @@
-use std::{env, fs, path::Path, process};
+use std::{env, path::Path, process};
 
 use anyhow::Result;
 
 use crate::{
-    config::get_compiler_options,
-    parsers::{extract_default_css_imports, extract_used_classes},
-    utils::{list_files_in_directory, process_relative_import, replace_aliases},
+    cache::Cache,
+    config::{get_compiler_options, get_linter_config},
+    parsers::UsedClassName,
+    style_flow::collect_used_classes,
+    utils::list_files_in_directory,
 };
 
 pub fn get_class_usages() -> Result<()> {
@@ -41,45 +43,36 @@ pub fn get_class_usages() -> Result<()> {
         process::exit(1);
     });
 
-    let dir = list_files_in_directory(Path::new(".").to_path_buf(), tsconfig.exclude);
-    for entry in &dir {
-        let path = entry.replace("\\", "/");
-
-        if path.ends_with(".tsx") || path.ends_with(".jsx") {
-            let code = fs::read_to_string(entry)?;
-            let imported_css = extract_default_css_imports(&code).unwrap_or_else(|e| {
-                eprintln!("Could not parse file: {}\n{}", entry, e);
-                process::exit(1);
-            });
-
-            for (mut style_path, class_names) in imported_css {
-                process_relative_import(Path::new(entry), &mut style_path)?;
-                replace_aliases(&mut style_path, tsconfig.compiler_options.paths.clone());
+    let config = get_linter_config().unwrap_or_else(|e| {
+        eprintln!(
+            "\n{}Error{}: Could not load css-linter.json ({})",
+            COLOR_RED, COLOR_RESET, e
+        );
+        process::exit(1);
+    });
 
-                if target_file.to_string_lossy().replace("\\", "/") != style_path.replace("./", "")
-                {
-                    continue;
-                }
+    let dir = list_files_in_directory(Path::new(".").to_path_buf(), &tsconfig.exclude);
+    let cache = Cache::open(&config);
+    let used_classnames = collect_used_classes(&dir, &tsconfig, &config, &cache)?;
 
-                let used_fields = extract_used_classes(&code, &class_names, path.clone())
-                    .unwrap_or_else(|e| {
-                        eprintln!("Could not parse file: {}\n{}", entry, e);
-                        process::exit(1);
-                    });
+    for (style_path, used_fields) in used_classnames {
+        if target_file.to_string_lossy().replace("\\", "/") != style_path.replace("./", "") {
+            continue;
+        }
 
-                for field in used_fields {
-                    if field.class_name == *class_name {
-                        println!(
-                            "{}:{}:{}:{}",
-                            path,
-                            field.line,
-                            field.column,
-                            field.class_name.len()
-                        );
-                    }
-                }
+        for field in used_fields {
+            if field.class_name == *class_name {
+                print_usage(&field.file_name, &field);
             }
         }
     }
     Ok(())
 }
+
+/// `file:line:column:end_line:end_column` of the class name
+fn print_usage(path: &str, field: &UsedClassName) {
+    println!(
+        "{}:{}:{}:{}:{}",
+        path, field.line, field.column, field.end_line, field.end_column
+    );
+}
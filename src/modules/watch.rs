@@ -0,0 +1,243 @@
+use std::{
+    collections::BTreeSet,
+    env,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    sync::mpsc,
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::{
+    cache::Cache,
+    config::{get_compiler_options, get_linter_config, LinterConfig, Properties},
+    modules::linter::{print_changes, Diagnostic, Project},
+    utils::list_files_in_directory,
+};
+
+/// Changes this close to each other are linted together, editors often save in several writes
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Files whose change reloads the whole project
+pub const CONFIG_FILES: [&str; 2] = ["tsconfig.json", "css-linter.json"];
+
+/// Path of a changed file the way the project walk lists it, `None` for files the walk skips
+pub fn project_entry(cwd: &Path, path: &Path, exclude: &[String]) -> Option<String> {
+    let relative = path.strip_prefix(cwd).unwrap_or(path);
+    let relative: PathBuf = relative
+        .components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect();
+
+    let is_skipped = relative.components().any(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        name.starts_with('.') || exclude.iter().any(|i| name == *i)
+    });
+    if relative.as_os_str().is_empty() || is_skipped {
+        return None;
+    }
+    Some(Path::new(".").join(relative).to_string_lossy().into_owned())
+}
+
+/// Reads a changed file again, a file that is gone is forgotten
+pub fn update_entry(project: &mut Project, entry: &str, config: &LinterConfig, cache: &Cache) {
+    let Err(e) = project.update(entry, config, cache) else {
+        return;
+    };
+
+    match e.downcast_ref::<std::io::Error>() {
+        Some(io) if io.kind() == ErrorKind::NotFound => project.remove(entry),
+        _ => eprintln!("Could not parse file: {}\n{}", entry, e),
+    }
+}
+
+/// Lints the project again on every change under the current directory, re-reading only
+/// the changed files, and prints the diagnostics that appeared or were resolved
+pub fn watch(
+    mut project: Project,
+    mut diagnostics: Vec<Diagnostic>,
+    mut tsconfig: Properties,
+    mut config: LinterConfig,
+    mut cache: Cache,
+    minify: bool,
+) -> Result<()> {
+    let cwd = env::current_dir()?;
+    let (sender, receiver) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&cwd, RecursiveMode::Recursive)?;
+    // Some platforms report canonical paths
+    let canonical_cwd = cwd.canonicalize()?;
+    // Each list of minified diagnostics ends with an empty line, including the first one
+    if minify {
+        println!();
+    }
+
+    loop {
+        let mut events = vec![receiver.recv()?];
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE) {
+            events.push(event);
+        }
+
+        let mut changed = BTreeSet::new();
+        for event in events {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("Could not watch files: {}", e);
+                    continue;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                let path = path.strip_prefix(&canonical_cwd).unwrap_or(path);
+                changed.extend(project_entry(&cwd, path, &tsconfig.exclude));
+            }
+        }
+        if changed.is_empty() {
+            continue;
+        }
+
+        let is_config_changed = changed.iter().any(|entry| {
+            CONFIG_FILES
+                .iter()
+                .any(|file| *entry == format!("./{}", file))
+        });
+        if is_config_changed {
+            match (get_compiler_options(), get_linter_config()) {
+                (Ok(new_tsconfig), Ok(new_config)) => {
+                    tsconfig = new_tsconfig;
+                    config = new_config;
+                }
+                (Err(e), _) => {
+                    eprintln!("Could not load tsconfig.json ({})", e);
+                    continue;
+                }
+                (_, Err(e)) => {
+                    eprintln!("Could not load css-linter.json ({})", e);
+                    continue;
+                }
+            }
+            cache = Cache::open(&config);
+            let dir = list_files_in_directory(Path::new(".").to_path_buf(), &tsconfig.exclude);
+            // A broken script joins the project again once it parses, like any other change
+            match Project::load_valid(&dir, &config, &cache) {
+                Ok((loaded, errors)) => {
+                    for (entry, e) in errors {
+                        eprintln!("Could not parse file: {}\n{}", entry, e);
+                    }
+                    project = loaded;
+                }
+                Err(e) => {
+                    eprintln!("Could not load the project ({})", e);
+                    continue;
+                }
+            }
+        } else {
+            for entry in &changed {
+                let path = Path::new(entry);
+                if path.is_dir() {
+                    // Directories created or renamed into the project bring their files along
+                    for file in list_files_in_directory(path.to_path_buf(), &tsconfig.exclude) {
+                        update_entry(&mut project, &file, &config, &cache);
+                    }
+                } else if path.exists() {
+                    update_entry(&mut project, entry, &config, &cache);
+                } else {
+                    project.remove(entry);
+                }
+            }
+        }
+
+        let linted = project
+            .used_classes(&tsconfig, &config, &cache)
+            .and_then(|used_classnames| project.diagnostics(&used_classnames, &tsconfig, &config));
+        let current = match linted {
+            Ok(current) => current,
+            Err(e) => {
+                eprintln!("Could not lint the project: {}", e);
+                continue;
+            }
+        };
+        print_changes(&diagnostics, &current, minify);
+        diagnostics = current;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, slice};
+
+    use super::*;
+
+    #[test]
+    fn project_entry_is_relative_to_the_root() {
+        let cwd = Path::new("/project");
+
+        assert_eq!(
+            project_entry(cwd, Path::new("/project/src/a.module.css"), &[]).as_deref(),
+            Some("./src/a.module.css")
+        );
+        assert_eq!(
+            project_entry(cwd, Path::new("./src/a.tsx"), &[]).as_deref(),
+            Some("./src/a.tsx")
+        );
+        assert_eq!(
+            project_entry(cwd, Path::new("css-linter.json"), &[]).as_deref(),
+            Some("./css-linter.json")
+        );
+    }
+
+    #[test]
+    fn project_entry_skips_hidden_and_excluded_paths() {
+        let cwd = Path::new("/project");
+        let exclude = ["node_modules".to_string()];
+
+        assert_eq!(project_entry(cwd, Path::new("/project"), &exclude), None);
+        assert_eq!(
+            project_entry(cwd, Path::new("/project/.git/index"), &exclude),
+            None
+        );
+        assert_eq!(
+            project_entry(
+                cwd,
+                Path::new("/project/.css-linter-cache/0.json"),
+                &exclude
+            ),
+            None
+        );
+        assert_eq!(
+            project_entry(
+                cwd,
+                Path::new("/project/node_modules/x/a.module.css"),
+                &exclude
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn update_entry_forgets_a_deleted_module() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = dir
+            .path()
+            .join("a.module.css")
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&entry, ".a {}").unwrap();
+        let config = LinterConfig {
+            cache: false,
+            ..Default::default()
+        };
+        let cache = Cache::open(&config);
+        let mut project = Project::load(slice::from_ref(&entry), &config, &cache).unwrap();
+
+        fs::remove_file(&entry).unwrap();
+        update_entry(&mut project, &entry, &config, &cache);
+
+        assert!(project.css_module(&entry.replace("\\", "/")).is_none());
+    }
+}
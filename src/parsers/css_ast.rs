@@ -0,0 +1,227 @@
+use super::{SourceSpan, Token};
+
+#[derive(Clone, Debug, Default)]
+pub struct Stylesheet {
+    pub rules: Vec<Rule>,
+    /// Every comment of the file in source order, kept aside so that
+    /// directives like `css-lint-disable-rule` can be matched by position
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Comment {
+    pub text: String,
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug)]
+pub enum Rule {
+    Style(StyleRule),
+    At(AtRule),
+}
+
+#[derive(Clone, Debug)]
+pub struct StyleRule {
+    pub selectors: SelectorList,
+    pub block: Block,
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug)]
+pub struct AtRule {
+    pub name: String,
+    pub prelude: Vec<Token>,
+    pub block: Option<Block>,
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Block {
+    pub items: Vec<BlockItem>,
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug)]
+pub enum BlockItem {
+    Declaration(Declaration),
+    Rule(Rule),
+}
+
+#[derive(Clone, Debug)]
+pub struct Declaration {
+    pub name: String,
+    pub value: Vec<Token>,
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SelectorList {
+    pub selectors: Vec<Selector>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Selector {
+    pub components: Vec<SelectorComponent>,
+    pub span: SourceSpan,
+}
+
+#[derive(Clone, Debug)]
+pub enum PseudoArgument {
+    Selectors(SelectorList),
+    Tokens(Vec<Token>),
+}
+
+#[derive(Clone, Debug)]
+pub enum SelectorComponent {
+    /// `.name`, the span covers the name without the dot.
+    /// Classes formed by `&suffix` keep the span of `&suffix`
+    Class {
+        name: String,
+        span: SourceSpan,
+    },
+    Id,
+    /// Sass `%placeholder`, only emitted through `@extend`
+    Placeholder(String),
+    Type,
+    Universal,
+    Attribute,
+    PseudoClass {
+        name: String,
+        argument: Option<PseudoArgument>,
+    },
+    PseudoElement {
+        argument: Option<PseudoArgument>,
+    },
+    /// `&`, reference to the parent rule's selector.
+    /// `&--modifier` and `&__element` keep the glued identifier as `suffix`
+    Nesting {
+        suffix: Option<String>,
+        span: SourceSpan,
+    },
+    /// Descendant, child, next-sibling or subsequent-sibling combinator
+    Combinator,
+}
+
+impl Stylesheet {
+    /// Visits every at-rule at any depth, in source order
+    pub fn for_each_at_rule<'a, F: FnMut(&'a AtRule)>(&'a self, f: &mut F) {
+        fn walk<'a, F: FnMut(&'a AtRule)>(rules: impl IntoIterator<Item = &'a Rule>, f: &mut F) {
+            for rule in rules {
+                match rule {
+                    Rule::Style(style) => walk(style.block.rules(), f),
+                    Rule::At(at) => {
+                        f(at);
+                        if let Some(block) = &at.block {
+                            walk(block.rules(), f);
+                        }
+                    }
+                }
+            }
+        }
+        walk(&self.rules, f);
+    }
+
+    /// Visits every declaration at any depth, in source order
+    pub fn for_each_declaration<'a, F: FnMut(&'a Declaration)>(&'a self, f: &mut F) {
+        fn walk<'a, F: FnMut(&'a Declaration)>(block: &'a Block, f: &mut F) {
+            for item in &block.items {
+                match item {
+                    BlockItem::Declaration(declaration) => f(declaration),
+                    BlockItem::Rule(Rule::Style(style)) => walk(&style.block, f),
+                    BlockItem::Rule(Rule::At(at)) => {
+                        if let Some(block) = &at.block {
+                            walk(block, f);
+                        }
+                    }
+                }
+            }
+        }
+        for rule in &self.rules {
+            match rule {
+                Rule::Style(style) => walk(&style.block, f),
+                Rule::At(at) => {
+                    if let Some(block) = &at.block {
+                        walk(block, f);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Block {
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.items.iter().filter_map(|item| match item {
+            BlockItem::Rule(rule) => Some(rule),
+            BlockItem::Declaration(_) => None,
+        })
+    }
+
+    pub fn declarations(&self) -> impl Iterator<Item = &Declaration> {
+        self.items.iter().filter_map(|item| match item {
+            BlockItem::Declaration(declaration) => Some(declaration),
+            BlockItem::Rule(_) => None,
+        })
+    }
+}
+
+impl SelectorList {
+    /// Visits every class selector, including ones nested inside pseudo-class arguments
+    pub fn for_each_class<F: FnMut(&str, SourceSpan)>(&self, f: &mut F) {
+        self.for_each_scoped_class(false, &mut |name, span, _| f(name, span));
+    }
+
+    /// Same as `for_each_class`, also telling whether the class is in CSS modules' global scope.
+    /// `is_global` is the scope the selectors start in.
+    pub fn for_each_scoped_class<F: FnMut(&str, SourceSpan, bool)>(
+        &self,
+        is_global: bool,
+        f: &mut F,
+    ) {
+        for selector in &self.selectors {
+            selector.for_each_scoped_class(is_global, f);
+        }
+    }
+
+    pub fn contains_class(&self, class_name: &str) -> bool {
+        let mut found = false;
+        self.for_each_class(&mut |name, _| found |= name == class_name);
+        found
+    }
+}
+
+impl Selector {
+    /// Walks classes left to right: `:global(.a)` and `:local(.a)` scope their argument,
+    /// a bare `:global` or `:local` switches the scope for the rest of the selector
+    pub fn for_each_scoped_class<F: FnMut(&str, SourceSpan, bool)>(
+        &self,
+        mut is_global: bool,
+        f: &mut F,
+    ) {
+        for component in &self.components {
+            match component {
+                SelectorComponent::Class { name, span } => f(name, *span, is_global),
+                SelectorComponent::PseudoClass { name, argument, .. }
+                    if name == "global" || name == "local" =>
+                {
+                    match argument {
+                        Some(PseudoArgument::Selectors(list)) => {
+                            list.for_each_scoped_class(name == "global", f)
+                        }
+                        Some(PseudoArgument::Tokens(_)) => {}
+                        None => is_global = name == "global",
+                    }
+                }
+                SelectorComponent::PseudoClass {
+                    argument: Some(PseudoArgument::Selectors(list)),
+                    ..
+                }
+                | SelectorComponent::PseudoElement {
+                    argument: Some(PseudoArgument::Selectors(list)),
+                    ..
+                } => list.for_each_scoped_class(is_global, f),
+                _ => {}
+            }
+        }
+    }
+}
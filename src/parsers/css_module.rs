@@ -0,0 +1,597 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    parse_selector_list, parse_stylesheet, resolve_style_rules, AtRule, Comment, Declaration,
+    Dialect, PseudoArgument, ResolvedRule, Rule, Selector, SelectorComponent, SourceSpan,
+    StyleRule, Stylesheet, Token, TokenKind,
+};
+
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct ClassName {
+    pub class_name: String,
+    /// Position of the name as written, without the dot of a class selector
+    pub line_index: usize,
+    pub column_index: usize,
+    /// Exclusive end of the name as written, which for a class formed by nesting
+    /// is the end of `&suffix` rather than of the resolved name
+    pub end_column_index: usize,
+    /// Declared under `:global`, so it is not exported by the CSS module
+    pub is_global: bool,
+}
+
+impl ClassName {
+    fn new(class_name: &str, span: SourceSpan, is_global: bool) -> Self {
+        Self {
+            class_name: class_name.to_string(),
+            line_index: span.start.line,
+            column_index: span.start.column,
+            end_column_index: if span.end.line == span.start.line {
+                span.end.column
+            } else {
+                span.start.column + class_name.chars().count()
+            },
+            is_global,
+        }
+    }
+}
+
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub enum ComposesFrom {
+    /// `composes: base;`
+    Local,
+    /// `composes: base from global;`
+    Global,
+    /// `composes: base from './other.module.css';`, the path as written
+    File(String),
+}
+
+/// One class listed in a `composes` declaration
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Composition {
+    /// Class of the rule declaring `composes`
+    pub class_name: String,
+    pub composed: ClassName,
+    pub from: ComposesFrom,
+}
+
+/// Key of an ICSS `:export` block, readable from TSX as `styles.key`
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct IcssExport {
+    pub name: String,
+    pub line_index: usize,
+    pub column_index: usize,
+    /// Excluded from the unused check by a `css-lint-disable-rule unused-export` comment
+    pub is_disabled: bool,
+}
+
+/// Name defined by `@value name: ...` or imported by `@value name from '...'`,
+/// readable from TSX as `styles.name`
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct CssValue {
+    pub name: String,
+    pub line_index: usize,
+    pub column_index: usize,
+    /// Excluded from the unused check by a `css-lint-disable-rule unused-value` comment
+    pub is_disabled: bool,
+}
+
+/// One name listed in `@value primary, accent as brand from './colors.module.css';`
+#[derive(Eq, Hash, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct ValueImport {
+    /// Name in the module it is imported from, positioned where it is written
+    pub imported: ClassName,
+    /// The path as written
+    pub from: String,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct CssModule {
+    pub classes: HashSet<ClassName>,
+    /// Classes whose styles are reused by other rules of the same stylesheet:
+    /// Sass `@extend .name`, Less `:extend(.name)` and Less mixin calls like `.name();`
+    pub reused_classes: HashSet<ClassName>,
+    pub compositions: Vec<Composition>,
+    pub icss_exports: HashSet<IcssExport>,
+    pub values: HashSet<CssValue>,
+    pub value_imports: Vec<ValueImport>,
+    /// Values referenced by declarations and at-rule preludes of the same stylesheet
+    pub reused_values: HashSet<ClassName>,
+}
+
+/// Lines excluded from `rule` by a `css-lint-disable-rule <rule>` comment:
+/// the line the comment ends on and the one right after it
+fn disabled_lines(comments: &[Comment], rule: &str) -> HashSet<usize> {
+    const DISABLE_RULE_FLAG: &str = "css-lint-disable-rule ";
+
+    comments
+        .iter()
+        .filter(|comment| {
+            comment
+                .text
+                .trim()
+                .strip_prefix(DISABLE_RULE_FLAG)
+                .is_some_and(|rest| rest.trim_start().starts_with(rule))
+        })
+        .flat_map(|comment| [comment.span.end.line, comment.span.end.line + 1])
+        .collect()
+}
+
+fn collect_classes(stylesheet: &Stylesheet, resolved: &[ResolvedRule]) -> HashSet<ClassName> {
+    let disabled = disabled_lines(&stylesheet.comments, "unused-class");
+
+    let mut classes = HashSet::new();
+    for rule in resolved {
+        rule.selectors
+            .for_each_scoped_class(false, &mut |name, span, is_global| {
+                if !disabled.contains(&span.start.line) {
+                    classes.insert(ClassName::new(name, span, is_global));
+                }
+            });
+    }
+    classes
+}
+
+fn collect_reused_classes(
+    stylesheet: &Stylesheet,
+    resolved: &[ResolvedRule],
+) -> HashSet<ClassName> {
+    let mut reused = HashSet::new();
+    let mut insert = |name: &str, span: SourceSpan| {
+        reused.insert(ClassName::new(name, span, false));
+    };
+
+    stylesheet.for_each_at_rule(&mut |at| match at.name.as_str() {
+        "extend" => parse_selector_list(&at.prelude).for_each_class(&mut insert),
+        // Calls of parametric mixins are not classes
+        "include" => parse_selector_list(&at.prelude).for_each_class(&mut |name, span| {
+            if resolved.iter().any(|r| r.selectors.contains_class(name)) {
+                insert(name, span);
+            }
+        }),
+        _ => {}
+    });
+
+    for rule in resolved {
+        for selector in &rule.selectors.selectors {
+            for component in &selector.components {
+                if let SelectorComponent::PseudoClass {
+                    name,
+                    argument: Some(PseudoArgument::Tokens(tokens)),
+                    ..
+                } = component
+                {
+                    if name == "extend" {
+                        parse_selector_list(tokens).for_each_class(&mut insert);
+                    }
+                }
+            }
+        }
+    }
+
+    reused
+}
+
+/// The class a selector ends with, e.g. `title` for `.card > .title:hover`
+fn subject_class(selector: &Selector) -> Option<&str> {
+    selector
+        .components
+        .iter()
+        .rev()
+        .take_while(|c| !matches!(c, SelectorComponent::Combinator))
+        .find_map(|c| match c {
+            SelectorComponent::Class { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+}
+
+fn collect_compositions(resolved: &[ResolvedRule]) -> Vec<Composition> {
+    let mut compositions = Vec::new();
+
+    for rule in resolved {
+        for declaration in rule.rule.block.declarations() {
+            if !declaration.name.eq_ignore_ascii_case("composes") {
+                continue;
+            }
+
+            let mut composed = Vec::new();
+            let mut from = ComposesFrom::Local;
+            let mut tokens = declaration.value.iter().filter(|t| !t.is_trivia());
+
+            while let Some(token) = tokens.next() {
+                match &token.kind {
+                    TokenKind::Ident(ident) if ident == "from" => {
+                        from = match tokens.next().map(|t| &t.kind) {
+                            Some(TokenKind::String(path)) => ComposesFrom::File(path.clone()),
+                            Some(TokenKind::Ident(ident)) if ident == "global" => {
+                                ComposesFrom::Global
+                            }
+                            _ => ComposesFrom::Local,
+                        };
+                        break;
+                    }
+                    TokenKind::Ident(ident) => {
+                        composed.push(ClassName::new(ident, token.span, false))
+                    }
+                    _ => {}
+                }
+            }
+
+            for selector in &rule.selectors.selectors {
+                let Some(class_name) = subject_class(selector) else {
+                    continue;
+                };
+                compositions.extend(composed.iter().map(|composed| Composition {
+                    class_name: class_name.to_string(),
+                    composed: composed.clone(),
+                    from: from.clone(),
+                }));
+            }
+        }
+    }
+
+    compositions
+}
+
+/// Checks whether the rule is an ICSS block like `:export {}` or `:import("./a.css") {}`
+fn is_icss_block(rule: &StyleRule, block_name: &str) -> bool {
+    match rule.selectors.selectors.as_slice() {
+        [selector] => matches!(
+            selector.components.as_slice(),
+            [SelectorComponent::PseudoClass { name, .. }] if name == block_name
+        ),
+        _ => false,
+    }
+}
+
+fn icss_export_blocks(stylesheet: &Stylesheet) -> impl Iterator<Item = &StyleRule> {
+    stylesheet.rules.iter().filter_map(|rule| match rule {
+        Rule::Style(style) if is_icss_block(style, "export") => Some(style),
+        _ => None,
+    })
+}
+
+fn collect_icss_exports(stylesheet: &Stylesheet) -> HashSet<IcssExport> {
+    let disabled = disabled_lines(&stylesheet.comments, "unused-export");
+
+    icss_export_blocks(stylesheet)
+        .flat_map(|rule| rule.block.declarations())
+        .map(|declaration| IcssExport {
+            name: declaration.name.clone(),
+            line_index: declaration.span.start.line,
+            column_index: declaration.span.start.column,
+            is_disabled: disabled.contains(&declaration.span.start.line),
+        })
+        .collect()
+}
+
+enum ValueRule<'a> {
+    /// `@value primary: #0c77f8;`, `value` may reference other values
+    Definition {
+        name: &'a Token,
+        value: Vec<&'a Token>,
+    },
+    /// `(imported, local)` names of `@value a, b as c from './colors.module.css';`
+    Import {
+        names: Vec<(&'a Token, &'a Token)>,
+        from: &'a Token,
+    },
+}
+
+fn ident(token: &Token) -> Option<&str> {
+    match &token.kind {
+        TokenKind::Ident(ident) => Some(ident),
+        _ => None,
+    }
+}
+
+fn parse_value_rule(at: &AtRule) -> Option<ValueRule<'_>> {
+    if at.name != "value" {
+        return None;
+    }
+    let tokens: Vec<&Token> = at.prelude.iter().filter(|t| !t.is_trivia()).collect();
+
+    if let [names @ .., from_keyword, from] = tokens.as_slice() {
+        let is_source = matches!(from.kind, TokenKind::String(_) | TokenKind::Ident(_));
+        if ident(from_keyword) == Some("from") && is_source {
+            let mut imports: Vec<(&Token, &Token)> = Vec::new();
+            let mut names = names.iter();
+            while let Some(token) = names.next() {
+                match ident(token) {
+                    Some("as") => {
+                        if let (Some(last), Some(alias)) = (imports.last_mut(), names.next()) {
+                            last.1 = alias;
+                        }
+                    }
+                    Some(_) => imports.push((token, token)),
+                    None => {}
+                }
+            }
+            return Some(ValueRule::Import {
+                names: imports,
+                from,
+            });
+        }
+    }
+
+    let (name, rest) = tokens.split_first()?;
+    ident(name)?;
+    let value = match rest.split_first() {
+        Some((colon, value)) if colon.kind == TokenKind::Colon => value.to_vec(),
+        _ => rest.to_vec(),
+    };
+    Some(ValueRule::Definition { name, value })
+}
+
+fn value_rules(stylesheet: &Stylesheet) -> impl Iterator<Item = (&AtRule, ValueRule<'_>)> {
+    stylesheet.rules.iter().filter_map(|rule| match rule {
+        Rule::At(at) => parse_value_rule(at).map(|value| (at, value)),
+        Rule::Style(_) => None,
+    })
+}
+
+fn collect_values(stylesheet: &Stylesheet) -> (HashSet<CssValue>, Vec<ValueImport>) {
+    let disabled = disabled_lines(&stylesheet.comments, "unused-value");
+    let value = |token: &Token| CssValue {
+        name: ident(token).unwrap_or_default().to_string(),
+        line_index: token.span.start.line,
+        column_index: token.span.start.column,
+        is_disabled: disabled.contains(&token.span.start.line),
+    };
+
+    let mut values = HashSet::new();
+    let mut definitions = Vec::new();
+    let mut import_rules = Vec::new();
+
+    for (_, rule) in value_rules(stylesheet) {
+        match rule {
+            ValueRule::Definition {
+                name,
+                value: tokens,
+            } => {
+                values.insert(value(name));
+                definitions.push((name, tokens));
+            }
+            ValueRule::Import { names, from } => {
+                values.extend(names.iter().map(|(_, local)| value(local)));
+                import_rules.push((names, from));
+            }
+        }
+    }
+
+    let mut imports = Vec::new();
+    for (names, from) in import_rules {
+        // `@value colors: './colors.module.css'; @value primary from colors;`
+        let path = match &from.kind {
+            TokenKind::String(path) => Some(path),
+            _ => definitions
+                .iter()
+                .find_map(|(name, value)| match value.as_slice() {
+                    [Token {
+                        kind: TokenKind::String(path),
+                        ..
+                    }] if name.kind == from.kind => Some(path),
+                    _ => None,
+                }),
+        };
+        let Some(path) = path else {
+            continue;
+        };
+
+        imports.extend(names.iter().map(|(imported, _)| ValueImport {
+            imported: ClassName::new(ident(imported).unwrap_or_default(), imported.span, false),
+            from: path.clone(),
+        }));
+    }
+
+    (values, imports)
+}
+
+/// Values referenced outside of their own definition, e.g. `color: primary` or `@media small`
+fn collect_reused_values(
+    stylesheet: &Stylesheet,
+    values: &HashSet<CssValue>,
+) -> HashSet<ClassName> {
+    let names: HashSet<&str> = values.iter().map(|v| v.name.as_str()).collect();
+    let mut reused = HashSet::new();
+    let mut scan = |tokens: &mut dyn Iterator<Item = &Token>| {
+        for token in tokens {
+            if let Some(name) = ident(token).filter(|name| names.contains(name)) {
+                reused.insert(ClassName::new(name, token.span, false));
+            }
+        }
+    };
+
+    stylesheet.for_each_declaration(&mut |declaration| scan(&mut declaration.value.iter()));
+    stylesheet.for_each_at_rule(&mut |at| match parse_value_rule(at) {
+        Some(ValueRule::Definition { value, .. }) => scan(&mut value.into_iter()),
+        // `from colors` reads the path from another value
+        Some(ValueRule::Import { from, .. }) => scan(&mut std::iter::once(from)),
+        None => scan(&mut at.prelude.iter()),
+    });
+
+    reused
+}
+
+pub fn analyze_css_module(css_content: &str, dialect: Dialect) -> CssModule {
+    let stylesheet = parse_stylesheet(css_content, dialect);
+    let resolved = resolve_style_rules(&stylesheet);
+    let (values, value_imports) = collect_values(&stylesheet);
+    let reused_values = collect_reused_values(&stylesheet, &values);
+
+    CssModule {
+        classes: collect_classes(&stylesheet, &resolved),
+        reused_classes: collect_reused_classes(&stylesheet, &resolved),
+        compositions: collect_compositions(&resolved),
+        icss_exports: collect_icss_exports(&stylesheet),
+        values,
+        value_imports,
+        reused_values,
+    }
+}
+
+/// Finds the first style rule in source order whose resolved selector mentions `class_name`
+pub fn find_class_rule<'a>(stylesheet: &'a Stylesheet, class_name: &str) -> Option<&'a StyleRule> {
+    resolve_style_rules(stylesheet)
+        .into_iter()
+        .find(|resolved| resolved.selectors.contains_class(class_name))
+        .map(|resolved| resolved.rule)
+}
+
+/// Finds the declaration of an ICSS `:export` key
+pub fn find_icss_export<'a>(stylesheet: &'a Stylesheet, key: &str) -> Option<&'a Declaration> {
+    icss_export_blocks(stylesheet)
+        .flat_map(|rule| rule.block.declarations())
+        .find(|declaration| declaration.name == key)
+}
+
+/// Finds the `@value` rule defining or importing `name`
+pub fn find_value_rule<'a>(stylesheet: &'a Stylesheet, name: &str) -> Option<&'a AtRule> {
+    value_rules(stylesheet)
+        .find(|(_, rule)| match rule {
+            ValueRule::Definition { name: defined, .. } => ident(defined) == Some(name),
+            ValueRule::Import { names, .. } => {
+                names.iter().any(|(_, local)| ident(local) == Some(name))
+            }
+        })
+        .map(|(at, _)| at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(class, composed, from, line, column)` of every composition, in source order
+    fn compositions(source: &str) -> Vec<(String, String, ComposesFrom, usize, usize)> {
+        analyze_css_module(source, Dialect::Css)
+            .compositions
+            .into_iter()
+            .map(|c| {
+                (
+                    c.class_name,
+                    c.composed.class_name,
+                    c.from,
+                    c.composed.line_index,
+                    c.composed.column_index,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn composes_a_local_class() {
+        let source = ".base { color: red }\n.card { composes: base; }";
+
+        assert_eq!(
+            compositions(source),
+            vec![("card".into(), "base".into(), ComposesFrom::Local, 1, 18)]
+        );
+    }
+
+    #[test]
+    fn composes_several_classes_from_a_file() {
+        let source = ".card {\n  composes: a b from './shared.module.css';\n}";
+
+        assert_eq!(
+            compositions(source),
+            vec![
+                (
+                    "card".into(),
+                    "a".into(),
+                    ComposesFrom::File("./shared.module.css".into()),
+                    1,
+                    12
+                ),
+                (
+                    "card".into(),
+                    "b".into(),
+                    ComposesFrom::File("./shared.module.css".into()),
+                    1,
+                    14
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn composes_from_global_and_for_every_selector() {
+        let source = ".a, .wrapper > .b:hover { composes: reset from global; }";
+
+        assert_eq!(
+            compositions(source),
+            vec![
+                ("a".into(), "reset".into(), ComposesFrom::Global, 0, 36),
+                ("b".into(), "reset".into(), ComposesFrom::Global, 0, 36),
+            ]
+        );
+    }
+
+    #[test]
+    fn export_keys_are_positioned_at_their_name() {
+        let source = ":export {\n  primary: #0c77f8;\n  /* css-lint-disable-rule unused-export */\n  gap: 4px;\n}\n.card :export { ignored: 1 }";
+
+        let mut exports: Vec<_> = analyze_css_module(source, Dialect::Css)
+            .icss_exports
+            .into_iter()
+            .map(|e| (e.name, e.line_index, e.column_index, e.is_disabled))
+            .collect();
+        exports.sort();
+
+        assert_eq!(
+            exports,
+            vec![("gap".into(), 3, 2, true), ("primary".into(), 1, 2, false),]
+        );
+    }
+
+    #[test]
+    fn values_are_defined_imported_and_reused() {
+        let source = "@value small: (max-width: 600px);\n@value primary, accent as brand from './colors.module.css';\n@value colors: './colors.module.css';\n@value gap from colors;\n@media small { .card { color: brand } }";
+
+        let module = analyze_css_module(source, Dialect::Css);
+        let mut values: Vec<_> = module
+            .values
+            .into_iter()
+            .map(|v| (v.name, v.line_index, v.column_index))
+            .collect();
+        values.sort();
+        let imports: Vec<_> = module
+            .value_imports
+            .into_iter()
+            .map(|i| (i.imported.class_name, i.imported.column_index, i.from))
+            .collect();
+        let mut reused: Vec<_> = module
+            .reused_values
+            .into_iter()
+            .map(|v| (v.class_name, v.line_index, v.column_index))
+            .collect();
+        reused.sort();
+
+        assert_eq!(
+            values,
+            vec![
+                ("brand".into(), 1, 26),
+                ("colors".into(), 2, 7),
+                ("gap".into(), 3, 7),
+                ("primary".into(), 1, 7),
+                ("small".into(), 0, 7),
+            ]
+        );
+        assert_eq!(
+            imports,
+            vec![
+                ("primary".into(), 7, "./colors.module.css".into()),
+                ("accent".into(), 16, "./colors.module.css".into()),
+                ("gap".into(), 7, "./colors.module.css".into()),
+            ]
+        );
+        assert_eq!(
+            reused,
+            vec![
+                ("brand".into(), 4, 30),
+                ("colors".into(), 3, 16),
+                ("small".into(), 4, 7),
+            ]
+        );
+    }
+}
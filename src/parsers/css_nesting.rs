@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    parse_selector_list, AtRule, Block, Rule, Selector, SelectorComponent, SelectorList, StyleRule,
+    Stylesheet, Token, TokenKind,
+};
+
+/// Style rule paired with its selector list after `&` substitution,
+/// i.e. the selectors the browser would actually match
+pub struct ResolvedRule<'a> {
+    pub rule: &'a StyleRule,
+    pub selectors: SelectorList,
+}
+
+fn is_keyframes(rule: &AtRule) -> bool {
+    rule.name.to_ascii_lowercase().ends_with("keyframes")
+}
+
+fn contains_nesting(selector: &Selector) -> bool {
+    selector
+        .components
+        .iter()
+        .any(|c| matches!(c, SelectorComponent::Nesting { .. }))
+}
+
+fn substitute(child: &Selector, parent: &Selector) -> Selector {
+    let mut components = Vec::new();
+
+    // Nested selectors without `&` are implicitly relative to the parent:
+    // `.card { .title {} }` is `.card .title`, `.card { > .title {} }` is `.card > .title`
+    if !contains_nesting(child) {
+        components.extend(parent.components.iter().cloned());
+        if !matches!(
+            child.components.first(),
+            Some(SelectorComponent::Combinator)
+        ) {
+            components.push(SelectorComponent::Combinator);
+        }
+        components.extend(child.components.iter().cloned());
+        return Selector {
+            components,
+            span: child.span,
+        };
+    }
+
+    for component in &child.components {
+        match component {
+            SelectorComponent::Nesting { suffix: None, .. } => {
+                components.extend(parent.components.iter().cloned())
+            }
+            SelectorComponent::Nesting {
+                suffix: Some(suffix),
+                span,
+            } => {
+                components.extend(parent.components.iter().cloned());
+                // `&--active` only forms a class when the parent selector ends with one
+                if let Some(SelectorComponent::Class { name, .. }) = components.last() {
+                    let name = format!("{}{}", name, suffix);
+                    components.pop();
+                    components.push(SelectorComponent::Class { name, span: *span });
+                }
+            }
+            other => components.push(other.clone()),
+        }
+    }
+
+    Selector {
+        components,
+        span: child.span,
+    }
+}
+
+/// Replaces every `&` in `child` with the selectors of `parent`
+pub fn resolve_nesting(child: &SelectorList, parent: Option<&SelectorList>) -> SelectorList {
+    let Some(parent) = parent else {
+        // Top-level `&` refers to the scoping root and matches nothing we care about
+        return SelectorList {
+            selectors: child
+                .selectors
+                .iter()
+                .map(|selector| Selector {
+                    components: selector
+                        .components
+                        .iter()
+                        .filter(|c| !matches!(c, SelectorComponent::Nesting { .. }))
+                        .cloned()
+                        .collect(),
+                    span: selector.span,
+                })
+                .collect(),
+        };
+    };
+
+    SelectorList {
+        selectors: child
+            .selectors
+            .iter()
+            .flat_map(|child| parent.selectors.iter().map(|p| substitute(child, p)))
+            .collect(),
+    }
+}
+
+fn contains_placeholder(selector: &Selector, extended: &HashSet<String>) -> bool {
+    selector
+        .components
+        .iter()
+        .any(|c| matches!(c, SelectorComponent::Placeholder(name) if !extended.contains(name)))
+}
+
+/// Name of the mixin referenced by an `@mixin` or `@include` prelude,
+/// without the module namespace of `@include theme.button`
+fn mixin_name(prelude: &[Token]) -> Option<&str> {
+    let mut name = None;
+    for token in prelude.iter().filter(|t| !t.is_trivia()) {
+        match &token.kind {
+            TokenKind::Ident(ident) if name.is_none() || ident != "using" => name = Some(ident),
+            TokenKind::Function(function) => return Some(function),
+            TokenKind::Delim('.') => {}
+            _ => break,
+        }
+    }
+    name.map(|n| n.as_str())
+}
+
+struct Resolver<'a> {
+    mixins: HashMap<&'a str, &'a Block>,
+    /// Placeholders targeted by `@extend`, their rules end up in the output
+    extended_placeholders: HashSet<String>,
+    include_stack: Vec<&'a str>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(stylesheet: &'a Stylesheet) -> Self {
+        let mut mixins = HashMap::new();
+        let mut extended_placeholders = HashSet::new();
+
+        stylesheet.for_each_at_rule(&mut |at| match at.name.as_str() {
+            "mixin" => {
+                if let (Some(name), Some(block)) = (mixin_name(&at.prelude), &at.block) {
+                    mixins.insert(name, block);
+                }
+            }
+            "extend" => {
+                for selector in parse_selector_list(&at.prelude).selectors {
+                    for component in selector.components {
+                        if let SelectorComponent::Placeholder(name) = component {
+                            extended_placeholders.insert(name);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        });
+
+        Self {
+            mixins,
+            extended_placeholders,
+            include_stack: Vec::new(),
+        }
+    }
+
+    fn resolve_rules(
+        &mut self,
+        rules: impl IntoIterator<Item = &'a Rule>,
+        parent: Option<&SelectorList>,
+        resolved: &mut Vec<ResolvedRule<'a>>,
+    ) {
+        for rule in rules {
+            match rule {
+                Rule::Style(style) => {
+                    let mut selectors = resolve_nesting(&style.selectors, parent);
+                    selectors
+                        .selectors
+                        .retain(|s| !contains_placeholder(s, &self.extended_placeholders));
+
+                    let mut nested = Vec::new();
+                    self.resolve_rules(style.block.rules(), Some(&selectors), &mut nested);
+
+                    resolved.push(ResolvedRule {
+                        rule: style,
+                        selectors,
+                    });
+                    resolved.extend(nested);
+                }
+                Rule::At(at) if is_keyframes(at) => {}
+                // Mixin bodies are only emitted where they are included
+                Rule::At(at) if at.name == "mixin" || at.name == "function" => {}
+                Rule::At(at) if at.name == "include" => {
+                    let mixin = mixin_name(&at.prelude)
+                        .filter(|name| !self.include_stack.contains(name))
+                        .and_then(|name| self.mixins.get_key_value(name))
+                        .map(|(name, block)| (*name, *block));
+
+                    if let Some((name, block)) = mixin {
+                        self.include_stack.push(name);
+                        self.resolve_rules(block.rules(), parent, resolved);
+                        self.include_stack.pop();
+                    }
+                    // Content block passed to the mixin through `@content`
+                    if let Some(block) = &at.block {
+                        self.resolve_rules(block.rules(), parent, resolved);
+                    }
+                }
+                // Less `@detached: { ... }` rulesets are only emitted when called
+                Rule::At(at) if at.prelude.first().map(|t| &t.kind) == Some(&TokenKind::Colon) => {}
+                // Conditional group rules like `@media` keep the enclosing selector
+                Rule::At(at) => {
+                    if let Some(block) = &at.block {
+                        self.resolve_rules(block.rules(), parent, resolved);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Flattens the stylesheet into its style rules in source order, with nesting resolved
+pub fn resolve_style_rules(stylesheet: &Stylesheet) -> Vec<ResolvedRule<'_>> {
+    let mut resolved = Vec::new();
+    Resolver::new(stylesheet).resolve_rules(&stylesheet.rules, None, &mut resolved);
+    resolved
+}
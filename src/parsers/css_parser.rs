@@ -1,132 +1,661 @@
-use std::collections::HashSet;
+use super::{
+    insert_indented_blocks, less_mixin_definition, less_statement, strip_less_guard, tokenize,
+    AtRule, Block, BlockItem, Comment, Declaration, Dialect, PseudoArgument, Rule, Selector,
+    SelectorComponent, SelectorList, SourceSpan, StyleRule, Stylesheet, Token, TokenKind,
+};
 
-fn is_char(c: char) -> bool {
-    c.is_alphabetic() || c.is_numeric() || c == '_' || c == '-'
+/// Pseudo-classes whose argument is itself a selector list
+const SELECTOR_PSEUDO_CLASSES: [&str; 13] = [
+    "global",
+    "local",
+    "not",
+    "is",
+    "where",
+    "has",
+    "matches",
+    "-webkit-any",
+    "-moz-any",
+    "host",
+    "host-context",
+    "slotted",
+    "cue",
+];
+
+struct CssParser {
+    tokens: Vec<Token>,
+    index: usize,
+    dialect: Dialect,
+}
+
+impl CssParser {
+    fn new(tokens: Vec<Token>, dialect: Dialect) -> Self {
+        Self {
+            tokens,
+            index: 0,
+            dialect,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|t| {
+            matches!(
+                t.kind,
+                TokenKind::Whitespace | TokenKind::Cdo | TokenKind::Cdc
+            )
+        }) {
+            self.index += 1;
+        }
+    }
+
+    fn last_span(&self) -> SourceSpan {
+        self.tokens
+            .get(self.index.saturating_sub(1))
+            .map(|t| t.span)
+            .unwrap_or_default()
+    }
+
+    /// Consumes component values until a top-level `{`, `;` or `}`.
+    /// The terminator itself is left in the stream.
+    fn consume_prelude(&mut self) -> Vec<Token> {
+        let mut prelude = Vec::new();
+        let mut depth = 0usize;
+
+        while let Some(token) = self.peek() {
+            match token.kind {
+                TokenKind::LeftBrace | TokenKind::Semicolon | TokenKind::RightBrace
+                    if depth == 0 =>
+                {
+                    break
+                }
+                TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Function(_) => {
+                    depth += 1
+                }
+                TokenKind::RightParen | TokenKind::RightBracket => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            prelude.extend(self.bump());
+        }
+
+        trim_tokens(&prelude).to_vec()
+    }
+
+    fn parse_rules(&mut self, top_level: bool) -> Vec<Rule> {
+        let mut rules = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            let Some(token) = self.peek() else {
+                break;
+            };
+
+            match token.kind {
+                TokenKind::RightBrace if !top_level => break,
+                TokenKind::RightBrace | TokenKind::Semicolon => {
+                    self.bump();
+                }
+                TokenKind::AtKeyword(_) => rules.push(Rule::At(self.parse_at_rule())),
+                _ => {
+                    let prelude = self.consume_prelude();
+                    rules.extend(self.parse_qualified_rule(prelude));
+                }
+            }
+        }
+
+        rules
+    }
+
+    fn parse_at_rule(&mut self) -> AtRule {
+        let Some(Token {
+            kind: TokenKind::AtKeyword(name),
+            span,
+        }) = self.bump()
+        else {
+            unreachable!("parse_at_rule must be called on an at-keyword");
+        };
+
+        let prelude = self.consume_prelude();
+        let block = match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::LeftBrace) => Some(self.parse_block()),
+            Some(TokenKind::Semicolon) => {
+                self.bump();
+                None
+            }
+            _ => None,
+        };
+
+        AtRule {
+            name,
+            prelude,
+            block,
+            span: span.to(self.last_span()),
+        }
+    }
+
+    /// Parses a qualified rule whose prelude has already been consumed.
+    /// A prelude that is not followed by a block is only meaningful as a Less mixin call.
+    fn parse_qualified_rule(&mut self, prelude: Vec<Token>) -> Option<Rule> {
+        match self.peek().map(|t| &t.kind) {
+            Some(TokenKind::LeftBrace) => {}
+            next => {
+                if next == Some(&TokenKind::Semicolon) {
+                    self.bump();
+                }
+                return match self.dialect {
+                    Dialect::Less => less_statement(&prelude).map(Rule::At),
+                    _ => None,
+                };
+            }
+        }
+
+        let block = self.parse_block();
+        let start = prelude.first().map_or(block.span, |t| t.span);
+        let span = start.to(block.span);
+
+        if self.dialect == Dialect::Less {
+            if let Some((name, name_span)) = less_mixin_definition(&prelude) {
+                return Some(Rule::At(AtRule {
+                    name: "mixin".into(),
+                    prelude: vec![Token {
+                        kind: TokenKind::Ident(name),
+                        span: name_span,
+                    }],
+                    block: Some(block),
+                    span,
+                }));
+            }
+        }
+
+        let selectors = match self.dialect {
+            Dialect::Less => parse_selector_list(strip_less_guard(&prelude)),
+            _ => parse_selector_list(&prelude),
+        };
+
+        Some(Rule::Style(StyleRule {
+            selectors,
+            span,
+            block,
+        }))
+    }
+
+    fn parse_block(&mut self) -> Block {
+        let open = self.bump().map(|t| t.span).unwrap_or_default();
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            let Some(token) = self.peek() else {
+                break;
+            };
+
+            match token.kind {
+                TokenKind::RightBrace => {
+                    self.bump();
+                    break;
+                }
+                TokenKind::Semicolon => {
+                    self.bump();
+                }
+                TokenKind::AtKeyword(_) => {
+                    items.push(BlockItem::Rule(Rule::At(self.parse_at_rule())))
+                }
+                _ => {
+                    let prelude = self.consume_prelude();
+                    match parse_declaration(&prelude) {
+                        Some(declaration)
+                            if self.peek().map(|t| &t.kind) != Some(&TokenKind::LeftBrace) =>
+                        {
+                            items.push(BlockItem::Declaration(declaration))
+                        }
+                        _ => items.extend(self.parse_qualified_rule(prelude).map(BlockItem::Rule)),
+                    }
+                }
+            }
+        }
+
+        Block {
+            items,
+            span: open.to(self.last_span()),
+        }
+    }
 }
 
-fn is_first_char_numeric(buffer: &str) -> bool {
-    buffer.chars().next().map_or(false, |c| c.is_numeric())
+fn trim_tokens(tokens: &[Token]) -> &[Token] {
+    let start = tokens
+        .iter()
+        .position(|t| !t.is_trivia())
+        .unwrap_or(tokens.len());
+    let end = tokens
+        .iter()
+        .rposition(|t| !t.is_trivia())
+        .map_or(start, |i| i + 1);
+    &tokens[start..end]
+}
+
+fn parse_declaration(tokens: &[Token]) -> Option<Declaration> {
+    let tokens = trim_tokens(tokens);
+    let (first, rest) = tokens.split_first()?;
+    let TokenKind::Ident(name) = &first.kind else {
+        return None;
+    };
+
+    let rest = trim_tokens(rest);
+    let (colon, value) = rest.split_first()?;
+    if colon.kind != TokenKind::Colon {
+        return None;
+    }
+
+    Some(Declaration {
+        name: name.clone(),
+        value: trim_tokens(value).to_vec(),
+        span: first.span.to(tokens[tokens.len() - 1].span),
+    })
 }
 
-fn contains_forbidden_characters(string: &str) -> bool {
-    string
-        .chars()
-        .filter(|char| ['\'', '"', '=', '(', '['].contains(char))
-        .collect::<Vec<char>>()
-        .len()
-        != 0
+fn is_interpolation(token: Option<&Token>) -> bool {
+    matches!(token.map(|t| &t.kind), Some(TokenKind::Interpolation(_)))
 }
 
-#[derive(Eq, Hash, PartialEq, Clone, Debug)]
-pub struct ClassName {
-    pub class_name: String,
-    pub line_index: usize,
-    pub column_index: usize,
+/// Returns the index of the last token glued to an interpolated name starting at `start`
+fn skip_interpolated(tokens: &[Token], start: usize) -> usize {
+    let mut end = start;
+    while tokens.get(end + 1).is_some_and(|t| {
+        matches!(
+            t.kind,
+            TokenKind::Interpolation(_)
+                | TokenKind::Ident(_)
+                | TokenKind::Number(_)
+                | TokenKind::Dimension(..)
+                | TokenKind::Delim('-')
+        )
+    }) {
+        end += 1;
+    }
+    end
 }
 
-// TODO: Do normal CSS parser
-pub fn extract_classes(css_content: &str) -> HashSet<ClassName> {
-    let mut defined_classes: HashSet<ClassName> = HashSet::new();
-    const DISABLE_RULE_FLAG: &str = "css-lint-disable-rule ";
-    let mut skip_lines_remaining = 0;
-    let mut in_multiline_comment = false;
-
-    for (index, line) in css_content.split('\n').enumerate() {
-        let stripped_line = line.trim_start();
-        let trimmed_line_indent = line.len() - stripped_line.len();
-
-        // Check for disable-rule directive before removing comments
-        if stripped_line.starts_with("/*") {
-            let comment_content = stripped_line
-                .trim_start_matches("/*")
-                .trim_end_matches("*/")
-                .trim();
-
-            if let Some(rest) = comment_content.strip_prefix(DISABLE_RULE_FLAG) {
-                if rest.trim_start().starts_with("unused-class") {
-                    skip_lines_remaining = 2;
+/// Returns the index of the token closing the group opened right before `start`
+fn find_group_end(tokens: &[Token], start: usize) -> usize {
+    let mut depth = 1usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token.kind {
+            TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Function(_) => depth += 1,
+            TokenKind::RightParen | TokenKind::RightBracket => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
                 }
             }
+            _ => {}
         }
-        if skip_lines_remaining != 0 {
-            skip_lines_remaining -= 1;
-            continue;
+    }
+    tokens.len()
+}
+
+fn split_top_level_commas(tokens: &[Token]) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token.kind {
+            TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Function(_) => depth += 1,
+            TokenKind::RightParen | TokenKind::RightBracket => depth = depth.saturating_sub(1),
+            TokenKind::Comma if depth == 0 => {
+                parts.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
         }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
 
-        // Remove comments from the line
-        let line_without_comments = remove_css_comments(stripped_line, &mut in_multiline_comment);
+pub fn parse_selector_list(tokens: &[Token]) -> SelectorList {
+    SelectorList {
+        selectors: split_top_level_commas(tokens)
+            .into_iter()
+            .map(parse_selector)
+            .filter(|s| !s.components.is_empty())
+            .collect(),
+    }
+}
+
+fn parse_pseudo_argument(name: &str, tokens: &[Token]) -> PseudoArgument {
+    let name = name.to_ascii_lowercase();
+    if SELECTOR_PSEUDO_CLASSES.contains(&name.as_str()) {
+        return PseudoArgument::Selectors(parse_selector_list(tokens));
+    }
 
-        if !line_without_comments.contains('.') || contains_forbidden_characters(&line_without_comments) {
-            continue;
+    // `:nth-child(2n + 1 of .item)`
+    if name.starts_with("nth-") {
+        if let Some(of) = tokens
+            .iter()
+            .position(|t| matches!(&t.kind, TokenKind::Ident(i) if i.eq_ignore_ascii_case("of")))
+        {
+            return PseudoArgument::Selectors(parse_selector_list(&tokens[of + 1..]));
         }
+    }
+
+    PseudoArgument::Tokens(tokens.to_vec())
+}
+
+pub fn parse_selector(tokens: &[Token]) -> Selector {
+    let tokens = trim_tokens(tokens);
+    let mut components = Vec::new();
+    let mut pending_descendant = false;
+    let mut i = 0;
 
-        let mut buffer: String = String::new();
-        let mut is_class = false;
-        let mut start_index = 0;
-        for (column_index, symbol) in line_without_comments.chars().enumerate() {
-            match symbol {
-                '.' => {
-                    if !buffer.is_empty() && !is_first_char_numeric(&buffer) {
-                        defined_classes.insert(ClassName {
-                            class_name: buffer.clone(),
-                            line_index: index,
-                            column_index: start_index + trimmed_line_indent,
-                        });
+    let push = |components: &mut Vec<SelectorComponent>,
+                pending: &mut bool,
+                component: SelectorComponent| {
+        if std::mem::take(pending)
+            && !matches!(
+                components.last(),
+                None | Some(SelectorComponent::Combinator)
+            )
+        {
+            components.push(SelectorComponent::Combinator);
+        }
+        components.push(component);
+    };
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match &token.kind {
+            TokenKind::Whitespace | TokenKind::Comment(_) => pending_descendant = true,
+            TokenKind::Delim('>' | '+' | '~') => {
+                pending_descendant = false;
+                components.push(SelectorComponent::Combinator);
+            }
+            TokenKind::Delim('.') => {
+                if let Some(Token {
+                    kind: TokenKind::Ident(name),
+                    span,
+                }) = tokens.get(i + 1)
+                {
+                    if is_interpolation(tokens.get(i + 2)) {
+                        // `.icon-#{$name}` is generated at compile time, the final name is unknown
+                        i = skip_interpolated(tokens, i + 1);
+                    } else {
+                        push(
+                            &mut components,
+                            &mut pending_descendant,
+                            SelectorComponent::Class {
+                                name: name.clone(),
+                                span: *span,
+                            },
+                        );
+                        i += 1;
                     }
-                    buffer.clear();
-                    start_index = column_index;
-                    is_class = true;
                 }
-                char_i => {
-                    if is_class && is_char(char_i) {
-                        buffer.push(char_i);
-                    } else if !buffer.is_empty() {
-                        if !is_first_char_numeric(&buffer) {
-                            defined_classes.insert(ClassName {
-                                class_name: buffer.clone(),
-                                line_index: index,
-                                column_index: start_index + trimmed_line_indent,
-                            });
-                        }
-                        buffer.clear();
-                        is_class = false;
+            }
+            TokenKind::Delim('&') => {
+                let (suffix, span) = match tokens.get(i + 1) {
+                    Some(Token {
+                        kind: TokenKind::Ident(suffix),
+                        span,
+                    }) if !is_interpolation(tokens.get(i + 2)) => {
+                        i += 1;
+                        (Some(suffix.clone()), token.span.to(*span))
+                    }
+                    _ => {
+                        i = skip_interpolated(tokens, i);
+                        (None, token.span)
                     }
+                };
+                push(
+                    &mut components,
+                    &mut pending_descendant,
+                    SelectorComponent::Nesting { suffix, span },
+                );
+            }
+            TokenKind::Delim('%') => {
+                if let Some(TokenKind::Ident(name)) = tokens.get(i + 1).map(|t| &t.kind) {
+                    push(
+                        &mut components,
+                        &mut pending_descendant,
+                        SelectorComponent::Placeholder(name.clone()),
+                    );
+                    i += 1;
                 }
             }
+            TokenKind::Delim('*') => push(
+                &mut components,
+                &mut pending_descendant,
+                SelectorComponent::Universal,
+            ),
+            TokenKind::Hash(_) => push(
+                &mut components,
+                &mut pending_descendant,
+                SelectorComponent::Id,
+            ),
+            TokenKind::Ident(_) => push(
+                &mut components,
+                &mut pending_descendant,
+                SelectorComponent::Type,
+            ),
+            TokenKind::LeftBracket => {
+                let end = find_group_end(tokens, i + 1);
+                push(
+                    &mut components,
+                    &mut pending_descendant,
+                    SelectorComponent::Attribute,
+                );
+                i = end;
+            }
+            TokenKind::Colon => {
+                let is_element =
+                    matches!(tokens.get(i + 1).map(|t| &t.kind), Some(TokenKind::Colon));
+                let name_index = if is_element { i + 2 } else { i + 1 };
+
+                let (name, argument, end) = match tokens.get(name_index).map(|t| &t.kind) {
+                    Some(TokenKind::Ident(name)) => (name.clone(), None, name_index),
+                    Some(TokenKind::Function(name)) => {
+                        let end = find_group_end(tokens, name_index + 1);
+                        let argument = parse_pseudo_argument(name, &tokens[name_index + 1..end]);
+                        (name.clone(), Some(argument), end)
+                    }
+                    _ => {
+                        i += 1;
+                        continue;
+                    }
+                };
+
+                let component = if is_element {
+                    SelectorComponent::PseudoElement { argument }
+                } else {
+                    SelectorComponent::PseudoClass { name, argument }
+                };
+                push(&mut components, &mut pending_descendant, component);
+                i = end;
+            }
+            _ => {}
         }
-        if !buffer.is_empty() && !is_first_char_numeric(&buffer) {
-            defined_classes.insert(ClassName {
-                class_name: buffer,
-                line_index: index,
-                column_index: start_index + trimmed_line_indent,
-            });
-        }
+        i += 1;
     }
 
-    defined_classes
-}
+    let span = match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => first.span.to(last.span),
+        _ => SourceSpan::default(),
+    };
 
-fn remove_css_comments(line: &str, in_comment: &mut bool) -> String {
-    let mut result = String::new();
-    let mut chars = line.chars().peekable();
+    Selector { components, span }
+}
 
-    while let Some(ch) = chars.next() {
-        if *in_comment {
-            // Inside a comment, look for */
-            if ch == '*' && chars.peek() == Some(&'/') {
-                chars.next(); // consume '/'
-                *in_comment = false;
+pub fn parse_stylesheet(css_content: &str, dialect: Dialect) -> Stylesheet {
+    let mut comments = Vec::new();
+    let mut tokens: Vec<Token> = tokenize(css_content, dialect)
+        .into_iter()
+        .filter(|token| match &token.kind {
+            TokenKind::Comment(text) => {
+                comments.push(Comment {
+                    text: text.clone(),
+                    span: token.span,
+                });
+                false
             }
-        } else {
-            // Outside a comment
-            if ch == '/' && chars.peek() == Some(&'*') {
-                chars.next(); // consume '*'
-                *in_comment = true;
-            } else {
-                result.push(ch);
+            _ => true,
+        })
+        .collect();
+
+    if dialect == Dialect::Sass {
+        tokens = insert_indented_blocks(tokens, css_content);
+    }
+
+    Stylesheet {
+        rules: CssParser::new(tokens, dialect).parse_rules(true),
+        comments,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::css_nesting::resolve_style_rules;
+
+    /// Classes of the top-level style rules with the line and column of their name
+    fn classes(source: &str) -> Vec<(String, usize, usize)> {
+        let stylesheet = parse_stylesheet(source, Dialect::Css);
+        let mut classes = Vec::new();
+        for rule in &stylesheet.rules {
+            if let Rule::Style(rule) = rule {
+                rule.selectors.for_each_class(&mut |name, span| {
+                    classes.push((name.to_string(), span.start.line, span.start.column))
+                });
             }
         }
+        classes
     }
 
-    result
+    #[test]
+    fn attribute_selector_with_quoted_value() {
+        assert_eq!(
+            classes(r#".btn[data-active="true"] { color: red }"#),
+            vec![("btn".to_string(), 0, 1)]
+        );
+    }
+
+    #[test]
+    fn class_inside_not() {
+        assert_eq!(
+            classes(".a:not(.b) { color: red }"),
+            vec![("a".to_string(), 0, 1), ("b".to_string(), 0, 8)]
+        );
+    }
+
+    #[test]
+    fn url_with_dots_is_not_a_class() {
+        assert_eq!(
+            classes(".bg { background: url(./img.v2.png) }"),
+            vec![("bg".to_string(), 0, 1)]
+        );
+    }
+
+    #[test]
+    fn number_with_leading_dot_is_not_a_class() {
+        assert_eq!(
+            classes(".fade { transition: opacity .5s; margin: .25em }"),
+            vec![("fade".to_string(), 0, 1)]
+        );
+    }
+
+    #[test]
+    fn comments_are_kept_aside() {
+        let source = "/* .notaclass */\n.a { /* .nor */ color: red }";
+        let stylesheet = parse_stylesheet(source, Dialect::Css);
+
+        assert_eq!(classes(source), vec![("a".to_string(), 1, 1)]);
+        let comments: Vec<&str> = stylesheet.comments.iter().map(|c| c.text.trim()).collect();
+        assert_eq!(comments, vec![".notaclass", ".nor"]);
+    }
+
+    /// Classes of every style rule once nesting is resolved, with the line and columns of their name
+    fn resolved_classes(source: &str, dialect: Dialect) -> Vec<(String, usize, usize, usize)> {
+        let stylesheet = parse_stylesheet(source, dialect);
+        let mut classes = Vec::new();
+        for rule in resolve_style_rules(&stylesheet) {
+            rule.selectors.for_each_class(&mut |name, span| {
+                classes.push((
+                    name.to_string(),
+                    span.start.line,
+                    span.start.column,
+                    span.end.column,
+                ))
+            });
+        }
+        classes
+    }
+
+    fn class(
+        name: &str,
+        line: usize,
+        column: usize,
+        end_column: usize,
+    ) -> (String, usize, usize, usize) {
+        (name.to_string(), line, column, end_column)
+    }
+
+    #[test]
+    fn suffixes_extend_the_parent_class() {
+        let source = ".card {\n  &--active {}\n  &__el { &--mod {} }\n}";
+
+        assert_eq!(
+            resolved_classes(source, Dialect::Scss),
+            vec![
+                class("card", 0, 1, 5),
+                class("card--active", 1, 2, 11),
+                class("card__el", 2, 2, 7),
+                class("card__el--mod", 2, 10, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_selectors_without_nesting_are_descendants() {
+        assert_eq!(
+            resolved_classes(".card { .title {} &:hover {} }", Dialect::Scss),
+            vec![
+                class("card", 0, 1, 5),
+                class("card", 0, 1, 5),
+                class("title", 0, 9, 14),
+                class("card", 0, 1, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn suffix_on_a_selector_list_expands_every_parent() {
+        assert_eq!(
+            resolved_classes(".a, .b { &-x {} .c {} }", Dialect::Scss),
+            vec![
+                class("a", 0, 1, 2),
+                class("b", 0, 5, 6),
+                class("a-x", 0, 9, 12),
+                class("b-x", 0, 9, 12),
+                class("a", 0, 1, 2),
+                class("c", 0, 17, 18),
+                class("b", 0, 5, 6),
+                class("c", 0, 17, 18),
+            ]
+        );
+    }
+
+    #[test]
+    fn suffix_without_a_parent_class_forms_no_class() {
+        assert_eq!(
+            resolved_classes("div { &--x {} }", Dialect::Scss),
+            Vec::<(String, usize, usize, usize)>::new()
+        );
+    }
 }
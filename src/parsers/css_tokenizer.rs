@@ -0,0 +1,594 @@
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug)]
+pub enum Dialect {
+    Css,
+    Scss,
+    /// Indented Sass syntax
+    Sass,
+    Less,
+}
+
+impl Dialect {
+    /// Detects the stylesheet dialect of a CSS module by its file name
+    pub fn from_path(path: &str) -> Option<Self> {
+        if path.ends_with(".module.css") {
+            Some(Dialect::Css)
+        } else if path.ends_with(".module.scss") {
+            Some(Dialect::Scss)
+        } else if path.ends_with(".module.sass") {
+            Some(Dialect::Sass)
+        } else if path.ends_with(".module.less") {
+            Some(Dialect::Less)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_sass_family(self) -> bool {
+        matches!(self, Dialect::Scss | Dialect::Sass)
+    }
+}
+
+pub fn is_style_module(path: &str) -> bool {
+    Dialect::from_path(path).is_some()
+}
+
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug, Default)]
+pub struct SourcePos {
+    /// Byte offset into the source text
+    pub offset: usize,
+    /// Zero-based line index
+    pub line: usize,
+    /// Zero-based column, counted in characters
+    pub column: usize,
+}
+
+#[derive(Eq, Hash, PartialEq, Clone, Copy, Debug, Default)]
+pub struct SourceSpan {
+    pub start: SourcePos,
+    pub end: SourcePos,
+}
+
+impl SourceSpan {
+    pub fn new(start: SourcePos, end: SourcePos) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the smallest span covering both `self` and `other`
+    pub fn to(self, other: SourceSpan) -> Self {
+        Self {
+            start: self.start,
+            end: other.end,
+        }
+    }
+
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start.offset..self.end.offset]
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum TokenKind {
+    Ident(String),
+    /// Identifier immediately followed by `(`, the parenthesis is part of the token
+    Function(String),
+    AtKeyword(String),
+    Hash(String),
+    String(String),
+    BadString,
+    Url(String),
+    BadUrl,
+    Number(String),
+    Percentage(String),
+    Dimension(String, String),
+    Delim(char),
+    /// Sass `#{...}` or Less `@{...}` interpolation with its raw contents
+    Interpolation(String),
+    Whitespace,
+    Comment(String),
+    Colon,
+    Semicolon,
+    Comma,
+    LeftBracket,
+    RightBracket,
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    Cdo,
+    Cdc,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: SourceSpan,
+}
+
+impl Token {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment(_))
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+fn is_name_char(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+fn is_newline(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\x0C')
+}
+
+/// Tokenizer following the CSS Syntax Level 3 tokenization rules.
+/// It never fails: malformed input produces `BadString`, `BadUrl` or `Delim` tokens.
+pub struct Tokenizer<'a> {
+    source: &'a str,
+    dialect: Dialect,
+    chars: Vec<(usize, char)>,
+    index: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(source: &'a str, dialect: Dialect) -> Self {
+        Self {
+            source,
+            dialect,
+            chars: source.char_indices().collect(),
+            index: 0,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    pub fn tokenize(mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token() {
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.chars.get(self.index + n).map(|(_, c)| *c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.peek_at(0)
+    }
+
+    fn position(&self) -> SourcePos {
+        SourcePos {
+            offset: self
+                .chars
+                .get(self.index)
+                .map_or(self.source.len(), |(offset, _)| *offset),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += 1;
+        // Treat `\r\n` as a single line break
+        if c == '\n' || c == '\x0C' || (c == '\r' && self.peek() != Some('\n')) {
+            self.line += 1;
+            self.column = 0;
+        } else if c != '\r' {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_valid_escape(&self, n: usize) -> bool {
+        self.peek_at(n) == Some('\\') && self.peek_at(n + 1).is_some_and(|c| !is_newline(c))
+    }
+
+    fn starts_ident(&self, n: usize) -> bool {
+        match self.peek_at(n) {
+            Some('-') => {
+                self.peek_at(n + 1)
+                    .is_some_and(|c| is_name_start(c) || c == '-')
+                    || self.starts_valid_escape(n + 1)
+            }
+            Some('\\') => self.starts_valid_escape(n),
+            Some(c) => is_name_start(c),
+            None => false,
+        }
+    }
+
+    fn starts_number(&self, n: usize) -> bool {
+        match self.peek_at(n) {
+            Some('+') | Some('-') => match self.peek_at(n + 1) {
+                Some(c) if c.is_ascii_digit() => true,
+                Some('.') => self.peek_at(n + 2).is_some_and(|c| c.is_ascii_digit()),
+                _ => false,
+            },
+            Some('.') => self.peek_at(n + 1).is_some_and(|c| c.is_ascii_digit()),
+            Some(c) => c.is_ascii_digit(),
+            None => false,
+        }
+    }
+
+    fn consume_escape(&mut self) -> char {
+        let mut hex = String::new();
+        while hex.len() < 6 && self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            hex.extend(self.bump());
+        }
+
+        if hex.is_empty() {
+            return self.bump().unwrap_or('\u{FFFD}');
+        }
+
+        if self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+
+        u32::from_str_radix(&hex, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .filter(|c| *c != '\0')
+            .unwrap_or('\u{FFFD}')
+    }
+
+    fn consume_name(&mut self) -> String {
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if is_name_char(c) => name.extend(self.bump()),
+                Some('\\') if self.starts_valid_escape(0) => {
+                    self.bump();
+                    name.push(self.consume_escape());
+                }
+                _ => break,
+            }
+        }
+        name
+    }
+
+    fn consume_number(&mut self) -> String {
+        let mut number = String::new();
+        if matches!(self.peek(), Some('+') | Some('-')) {
+            number.extend(self.bump());
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            number.extend(self.bump());
+        }
+        if self.peek() == Some('.') && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) {
+            number.extend(self.bump());
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                number.extend(self.bump());
+            }
+        }
+        if matches!(self.peek(), Some('e') | Some('E')) {
+            let digit_at = match self.peek_at(1) {
+                Some('+') | Some('-') => 2,
+                _ => 1,
+            };
+            if self.peek_at(digit_at).is_some_and(|c| c.is_ascii_digit()) {
+                for _ in 0..digit_at {
+                    number.extend(self.bump());
+                }
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    number.extend(self.bump());
+                }
+            }
+        }
+        number
+    }
+
+    fn consume_numeric(&mut self) -> TokenKind {
+        let number = self.consume_number();
+        if self.starts_ident(0) {
+            TokenKind::Dimension(number, self.consume_name())
+        } else if self.peek() == Some('%') {
+            self.bump();
+            TokenKind::Percentage(number)
+        } else {
+            TokenKind::Number(number)
+        }
+    }
+
+    fn consume_string(&mut self, quote: char) -> TokenKind {
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                None => return TokenKind::String(value),
+                Some(c) if c == quote => {
+                    self.bump();
+                    return TokenKind::String(value);
+                }
+                Some(c) if is_newline(c) => return TokenKind::BadString,
+                Some('\\') => {
+                    self.bump();
+                    match self.peek() {
+                        None => {}
+                        Some(c) if is_newline(c) => {
+                            self.bump();
+                        }
+                        Some(_) => value.push(self.consume_escape()),
+                    }
+                }
+                Some(_) => value.extend(self.bump()),
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn consume_bad_url(&mut self) -> TokenKind {
+        while let Some(c) = self.bump() {
+            if c == ')' {
+                break;
+            }
+            if c == '\\' && self.peek().is_some() {
+                self.bump();
+            }
+        }
+        TokenKind::BadUrl
+    }
+
+    fn consume_url(&mut self) -> TokenKind {
+        let mut value = String::new();
+        self.skip_whitespace();
+        loop {
+            match self.peek() {
+                None => return TokenKind::Url(value),
+                Some(')') => {
+                    self.bump();
+                    return TokenKind::Url(value);
+                }
+                Some(c) if c.is_whitespace() => {
+                    self.skip_whitespace();
+                    return match self.peek() {
+                        None => TokenKind::Url(value),
+                        Some(')') => {
+                            self.bump();
+                            TokenKind::Url(value)
+                        }
+                        Some(_) => self.consume_bad_url(),
+                    };
+                }
+                Some('"') | Some('\'') | Some('(') => return self.consume_bad_url(),
+                Some('\\') if self.starts_valid_escape(0) => {
+                    self.bump();
+                    value.push(self.consume_escape());
+                }
+                Some('\\') => return self.consume_bad_url(),
+                Some(_) => value.extend(self.bump()),
+            }
+        }
+    }
+
+    fn consume_ident_like(&mut self) -> TokenKind {
+        let name = self.consume_name();
+        if self.peek() != Some('(') {
+            return TokenKind::Ident(name);
+        }
+
+        self.bump();
+        if name.eq_ignore_ascii_case("url") {
+            let mut lookahead = 0;
+            while self.peek_at(lookahead).is_some_and(|c| c.is_whitespace()) {
+                lookahead += 1;
+            }
+            if !matches!(self.peek_at(lookahead), Some('"') | Some('\'')) {
+                return self.consume_url();
+            }
+        }
+        TokenKind::Function(name)
+    }
+
+    fn consume_comment(&mut self) -> TokenKind {
+        // Opening `/*`
+        self.bump();
+        self.bump();
+
+        let mut text = String::new();
+        while let Some(c) = self.peek() {
+            if c == '*' && self.peek_at(1) == Some('/') {
+                self.bump();
+                self.bump();
+                break;
+            }
+            text.extend(self.bump());
+        }
+        TokenKind::Comment(text)
+    }
+
+    fn consume_line_comment(&mut self) -> TokenKind {
+        // Opening `//`
+        self.bump();
+        self.bump();
+
+        let mut text = String::new();
+        while self.peek().is_some_and(|c| !is_newline(c)) {
+            text.extend(self.bump());
+        }
+        TokenKind::Comment(text)
+    }
+
+    fn consume_interpolation(&mut self) -> TokenKind {
+        // Opening `#{` or `@{`
+        self.bump();
+        self.bump();
+
+        let mut text = String::new();
+        let mut depth = 0usize;
+        while let Some(c) = self.bump() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            text.push(c);
+        }
+        TokenKind::Interpolation(text)
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        let start = self.position();
+        let c = self.peek()?;
+
+        let kind = match c {
+            '/' if self.peek_at(1) == Some('*') => self.consume_comment(),
+            '/' if self.dialect != Dialect::Css && self.peek_at(1) == Some('/') => {
+                self.consume_line_comment()
+            }
+            '#' if self.dialect.is_sass_family() && self.peek_at(1) == Some('{') => {
+                self.consume_interpolation()
+            }
+            '@' if self.dialect == Dialect::Less && self.peek_at(1) == Some('{') => {
+                self.consume_interpolation()
+            }
+            c if c.is_whitespace() => {
+                self.skip_whitespace();
+                TokenKind::Whitespace
+            }
+            '"' | '\'' => {
+                self.bump();
+                self.consume_string(c)
+            }
+            '#' if self.peek_at(1).is_some_and(is_name_char) || self.starts_valid_escape(1) => {
+                self.bump();
+                TokenKind::Hash(self.consume_name())
+            }
+            '@' if self.starts_ident(1) => {
+                self.bump();
+                TokenKind::AtKeyword(self.consume_name())
+            }
+            '<' if self.peek_at(1) == Some('!')
+                && self.peek_at(2) == Some('-')
+                && self.peek_at(3) == Some('-') =>
+            {
+                (0..4).for_each(|_| {
+                    self.bump();
+                });
+                TokenKind::Cdo
+            }
+            '-' if self.peek_at(1) == Some('-') && self.peek_at(2) == Some('>') => {
+                (0..3).for_each(|_| {
+                    self.bump();
+                });
+                TokenKind::Cdc
+            }
+            _ if self.starts_number(0) => self.consume_numeric(),
+            _ if self.starts_ident(0) => self.consume_ident_like(),
+            _ => {
+                self.bump();
+                match c {
+                    ':' => TokenKind::Colon,
+                    ';' => TokenKind::Semicolon,
+                    ',' => TokenKind::Comma,
+                    '[' => TokenKind::LeftBracket,
+                    ']' => TokenKind::RightBracket,
+                    '(' => TokenKind::LeftParen,
+                    ')' => TokenKind::RightParen,
+                    '{' => TokenKind::LeftBrace,
+                    '}' => TokenKind::RightBrace,
+                    c => TokenKind::Delim(c),
+                }
+            }
+        };
+
+        Some(Token {
+            kind,
+            span: SourceSpan::new(start, self.position()),
+        })
+    }
+}
+
+pub fn tokenize(source: &str, dialect: Dialect) -> Vec<Token> {
+    Tokenizer::new(source, dialect).tokenize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source, Dialect::Css)
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn attribute_selector_with_quoted_value() {
+        assert_eq!(
+            kinds(r#".btn[data-active="true"]"#),
+            vec![
+                TokenKind::Delim('.'),
+                TokenKind::Ident("btn".into()),
+                TokenKind::LeftBracket,
+                TokenKind::Ident("data-active".into()),
+                TokenKind::Delim('='),
+                TokenKind::String("true".into()),
+                TokenKind::RightBracket,
+            ]
+        );
+    }
+
+    #[test]
+    fn url_with_dots_is_a_single_token() {
+        assert_eq!(
+            kinds("background: url(./img.v2.png);"),
+            vec![
+                TokenKind::Ident("background".into()),
+                TokenKind::Colon,
+                TokenKind::Url("./img.v2.png".into()),
+                TokenKind::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn number_with_leading_dot() {
+        assert_eq!(
+            kinds("transition: all .5s"),
+            vec![
+                TokenKind::Ident("transition".into()),
+                TokenKind::Colon,
+                TokenKind::Ident("all".into()),
+                TokenKind::Dimension(".5".into(), "s".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn comment_keeps_its_text_and_span() {
+        let tokens = tokenize("a\n/* .notaclass */ b", Dialect::Css);
+        let comment = tokens
+            .iter()
+            .find(|token| matches!(token.kind, TokenKind::Comment(_)))
+            .unwrap();
+
+        assert_eq!(comment.kind, TokenKind::Comment(" .notaclass ".into()));
+        assert_eq!(comment.span.start.line, 1);
+        assert_eq!(comment.span.start.column, 0);
+        assert_eq!(comment.span.end.column, 16);
+    }
+
+    #[test]
+    fn positions_count_characters() {
+        let tokens = tokenize(".é .b", Dialect::Css);
+        let b = tokens
+            .iter()
+            .find(|token| token.kind == TokenKind::Ident("b".into()))
+            .unwrap();
+
+        assert_eq!(b.span.start.column, 4);
+        assert_eq!(b.span.start.offset, 5);
+    }
+}
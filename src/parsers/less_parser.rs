@@ -0,0 +1,182 @@
+use super::{AtRule, SourceSpan, Token, TokenKind};
+
+fn significant(tokens: &[Token]) -> impl Iterator<Item = &Token> {
+    tokens.iter().filter(|t| !t.is_trivia())
+}
+
+/// Cuts a `when (...)` guard off a selector prelude
+pub fn strip_less_guard(prelude: &[Token]) -> &[Token] {
+    let mut depth = 0usize;
+    for (i, token) in prelude.iter().enumerate() {
+        match &token.kind {
+            TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Function(_) => depth += 1,
+            TokenKind::RightParen | TokenKind::RightBracket => depth = depth.saturating_sub(1),
+            TokenKind::Ident(ident) if depth == 0 && ident == "when" => return &prelude[..i],
+            _ => {}
+        }
+    }
+    prelude
+}
+
+/// Name of the parametric mixin declared by a prelude like `.button(@color; @size: 2px) when (...)`.
+/// Such rules are never emitted, so their selector is not an exported class.
+pub fn less_mixin_definition(prelude: &[Token]) -> Option<(String, SourceSpan)> {
+    let mut tokens = significant(prelude);
+    let first = tokens.next()?;
+    let name = tokens.next()?;
+
+    match (&first.kind, &name.kind) {
+        (TokenKind::Delim('.'), TokenKind::Function(name_str)) => {
+            Some((name_str.clone(), first.span.to(name.span)))
+        }
+        _ => None,
+    }
+}
+
+fn mixin_call_name(tokens: &[Token]) -> Option<(String, SourceSpan)> {
+    let mut name = None;
+    let mut depth = 0usize;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match &token.kind {
+            TokenKind::Delim('.') if depth == 0 => {
+                name = match tokens.get(i + 1) {
+                    Some(Token {
+                        kind: TokenKind::Ident(n) | TokenKind::Function(n),
+                        span,
+                    }) => Some((n.clone(), *span)),
+                    _ => None,
+                };
+            }
+            TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Function(_) => depth += 1,
+            TokenKind::RightParen | TokenKind::RightBracket => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    name
+}
+
+/// Turns a semicolon-terminated Less statement that is not a declaration into the
+/// equivalent Sass at-rule: `.bordered();` and `#ns > .mixin;` become `@include`,
+/// `&:extend(.base all);` becomes `@extend`
+pub fn less_statement(tokens: &[Token]) -> Option<AtRule> {
+    let mut significant = significant(tokens);
+    let first = significant.next()?;
+    let span = first.span.to(tokens[tokens.len() - 1].span);
+
+    if first.kind == TokenKind::Delim('&') {
+        let colon = significant.next()?;
+        let extend = significant.next()?;
+        if colon.kind != TokenKind::Colon
+            || !matches!(&extend.kind, TokenKind::Function(name) if name == "extend")
+        {
+            return None;
+        }
+
+        let start = tokens.iter().position(|t| t == extend)? + 1;
+        let end = tokens
+            .iter()
+            .rposition(|t| t.kind == TokenKind::RightParen)
+            .unwrap_or(tokens.len());
+        return Some(AtRule {
+            name: "extend".into(),
+            prelude: tokens[start..end.max(start)].to_vec(),
+            block: None,
+            span,
+        });
+    }
+
+    if !matches!(first.kind, TokenKind::Delim('.') | TokenKind::Hash(_)) {
+        return None;
+    }
+
+    let (name, name_span) = mixin_call_name(tokens)?;
+    Some(AtRule {
+        name: "include".into(),
+        // Keep the dot, so the call reads as a class reference
+        prelude: vec![
+            Token {
+                kind: TokenKind::Delim('.'),
+                span: SourceSpan::new(name_span.start, name_span.start),
+            },
+            Token {
+                kind: TokenKind::Ident(name),
+                span: name_span,
+            },
+        ],
+        block: None,
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::parsers::{analyze_css_module, ClassName, Dialect};
+
+    type Positioned = Vec<(String, usize, usize)>;
+
+    fn positioned(classes: HashSet<ClassName>) -> Positioned {
+        let mut classes: Positioned = classes
+            .into_iter()
+            .map(|c| (c.class_name, c.line_index, c.column_index))
+            .collect();
+        classes.sort_by(|a, b| (a.1, a.2, &a.0).cmp(&(b.1, b.2, &b.0)));
+        classes
+    }
+
+    /// Classes and reused classes of a Less module, with the line and column of their name
+    fn analyze(source: &str) -> (Positioned, Positioned) {
+        let module = analyze_css_module(source, Dialect::Less);
+        (
+            positioned(module.classes),
+            positioned(module.reused_classes),
+        )
+    }
+
+    fn class(name: &str, line: usize, column: usize) -> (String, usize, usize) {
+        (name.to_string(), line, column)
+    }
+
+    #[test]
+    fn mixin_calls_reuse_the_class() {
+        let source = ".bordered { border: 1px }\n.card {\n  .bordered();\n  #ns > .rounded;\n}";
+
+        let (classes, reused) = analyze(source);
+        assert_eq!(classes, vec![class("bordered", 0, 1), class("card", 1, 1)]);
+        assert_eq!(reused, vec![class("bordered", 2, 3)]);
+    }
+
+    #[test]
+    fn parametric_mixins_are_not_classes() {
+        let source = ".button(@color; @size: 2px) { color: @color }\n.card { .button(red); }";
+
+        let (classes, reused) = analyze(source);
+        assert_eq!(classes, vec![class("card", 1, 1)]);
+        assert!(reused.is_empty());
+    }
+
+    #[test]
+    fn guards_are_cut_off_the_selector() {
+        let source = ".card when (@mode = dark) { color: white }\n.theme(@a) when (iscolor(@a)) and (.b) { color: @a }\n.list when (default()) { .item { margin: 0 } }";
+
+        let (classes, _) = analyze(source);
+        assert_eq!(
+            classes,
+            vec![
+                class("card", 0, 1),
+                class("list", 2, 1),
+                class("item", 2, 26)
+            ]
+        );
+    }
+
+    #[test]
+    fn extend_reuses_the_class() {
+        let source = ".base { color: red }\n.card {\n  &:extend(.base all);\n}\n.title:extend(.base) { margin: 0 }";
+
+        let (_, reused) = analyze(source);
+        assert_eq!(reused, vec![class("base", 2, 12), class("base", 4, 15)]);
+    }
+}
@@ -1,5 +1,25 @@
+mod css_ast;
+mod css_module;
+mod css_nesting;
 mod css_parser;
+mod css_tokenizer;
+mod less_parser;
+mod sass_parser;
+mod tsx_class_helpers;
+mod tsx_flow;
+mod tsx_key_resolver;
 mod tsx_parser;
+mod tsx_scope;
 
+pub use css_ast::*;
+pub use css_module::*;
+pub use css_nesting::*;
 pub use css_parser::*;
-pub use tsx_parser::*;
\ No newline at end of file
+pub use css_tokenizer::*;
+pub use less_parser::*;
+pub use sass_parser::*;
+pub use tsx_class_helpers::*;
+pub use tsx_flow::*;
+pub use tsx_key_resolver::*;
+pub use tsx_parser::*;
+pub use tsx_scope::*;
@@ -0,0 +1,180 @@
+use std::mem;
+
+use super::{SourceSpan, Token, TokenKind};
+
+fn synthetic(kind: TokenKind, at: SourceSpan) -> Token {
+    Token {
+        kind,
+        span: SourceSpan::new(at.end, at.end),
+    }
+}
+
+/// Splits the token stream into logical lines. Line breaks inside parentheses
+/// and after a trailing comma (multi-line selector lists) do not end a line.
+fn split_lines(tokens: Vec<Token>, source: &str) -> Vec<Vec<Token>> {
+    let mut lines = Vec::new();
+    let mut current: Vec<Token> = Vec::new();
+    let mut depth = 0usize;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::Whitespace if depth == 0 && token.span.slice(source).contains('\n') => {
+                let continues = current
+                    .iter()
+                    .rev()
+                    .find(|t| !t.is_trivia())
+                    .is_some_and(|t| t.kind == TokenKind::Comma);
+
+                if continues {
+                    current.push(token);
+                } else if !current.is_empty() {
+                    lines.push(mem::take(&mut current));
+                }
+                continue;
+            }
+            TokenKind::Whitespace if current.is_empty() => continue,
+            TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Function(_) => depth += 1,
+            TokenKind::RightParen | TokenKind::RightBracket => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        current.push(token);
+    }
+
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Expands the `=mixin` and `+include` shorthands into their at-rule form
+fn expand_shorthand(line: &mut [Token]) {
+    let glued_name = matches!(
+        line.get(1).map(|t| &t.kind),
+        Some(TokenKind::Ident(_)) | Some(TokenKind::Function(_))
+    );
+    if !glued_name {
+        return;
+    }
+
+    let name = match line[0].kind {
+        TokenKind::Delim('=') => "mixin",
+        TokenKind::Delim('+') => "include",
+        _ => return,
+    };
+    line[0].kind = TokenKind::AtKeyword(name.to_string());
+}
+
+/// Converts indentation-based Sass into the brace-delimited token stream the CSS parser expects.
+/// Synthetic `{`, `}` and `;` tokens are zero-width, so every real token keeps its source position.
+pub fn insert_indented_blocks(tokens: Vec<Token>, source: &str) -> Vec<Token> {
+    let mut output = Vec::new();
+    let mut open_blocks: Vec<usize> = Vec::new();
+    let mut previous: Option<(usize, SourceSpan)> = None;
+
+    for mut line in split_lines(tokens, source) {
+        let indent = line[0].span.start.column;
+        expand_shorthand(&mut line);
+
+        if let Some((previous_indent, previous_end)) = previous {
+            if indent > previous_indent {
+                output.push(synthetic(TokenKind::LeftBrace, previous_end));
+                open_blocks.push(previous_indent);
+            } else {
+                output.push(synthetic(TokenKind::Semicolon, previous_end));
+                while open_blocks.last().is_some_and(|open| *open >= indent) {
+                    open_blocks.pop();
+                    output.push(synthetic(TokenKind::RightBrace, previous_end));
+                }
+            }
+        }
+
+        previous = Some((indent, line[line.len() - 1].span));
+        output.extend(line);
+    }
+
+    if let Some((_, previous_end)) = previous {
+        output.push(synthetic(TokenKind::Semicolon, previous_end));
+        for _ in open_blocks {
+            output.push(synthetic(TokenKind::RightBrace, previous_end));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsers::{analyze_css_module, ClassName, Dialect};
+
+    /// Classes of a module with the line and column of their name, in source order
+    fn classes(source: &str, dialect: Dialect) -> Vec<(String, usize, usize)> {
+        let mut classes: Vec<_> = analyze_css_module(source, dialect)
+            .classes
+            .into_iter()
+            .map(|c: ClassName| (c.class_name, c.line_index, c.column_index))
+            .collect();
+        classes.sort_by(|a, b| (a.1, a.2, &a.0).cmp(&(b.1, b.2, &b.0)));
+        classes
+    }
+
+    fn class(name: &str, line: usize, column: usize) -> (String, usize, usize) {
+        (name.to_string(), line, column)
+    }
+
+    #[test]
+    fn suffix_under_an_indented_selector_list() {
+        let source = ".a,\n.b\n  color: red\n  &__x\n    color: blue\n";
+
+        assert_eq!(
+            classes(source, Dialect::Sass),
+            vec![
+                class("a", 0, 1),
+                class("b", 1, 1),
+                class("a__x", 3, 2),
+                class("b__x", 3, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn line_comments_do_not_break_indented_blocks() {
+        let source = ".card\n  color: red\n// outdented comment\n  // comment\n  &__title\n    color: blue\n  .inner\n    margin: 0\n.next\n  color: green\n";
+
+        assert_eq!(
+            classes(source, Dialect::Sass),
+            vec![
+                class("card", 0, 1),
+                class("card__title", 4, 2),
+                class("inner", 6, 3),
+                class("next", 8, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn indented_mixins_emit_their_classes_where_included() {
+        let source = "=themed\n  .icon\n    color: red\n.card\n  +themed\n";
+
+        assert_eq!(
+            classes(source, Dialect::Sass),
+            vec![class("icon", 1, 3), class("card", 3, 1)]
+        );
+    }
+
+    #[test]
+    fn mixins_that_are_never_included_have_no_classes() {
+        let source = "@mixin unused { .ghost { color: red } }\n.card { color: blue }";
+
+        assert_eq!(classes(source, Dialect::Scss), vec![class("card", 1, 1)]);
+    }
+
+    #[test]
+    fn placeholders_only_emit_their_classes_once_extended() {
+        let source = "%base { color: red }\n.btn { @extend %base; }\n%base .label { margin: 0 }\n%unused .ghost { margin: 0 }";
+
+        assert_eq!(
+            classes(source, Dialect::Scss),
+            vec![class("btn", 1, 1), class("label", 2, 7)]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+use swc_ecma_ast::{
+    CallExpr, Callee, Expr, ImportSpecifier, MemberProp, Module, ModuleDecl, ModuleItem, Pat,
+    VarDeclarator,
+};
+use swc_ecma_visit::{Visit, VisitWith};
+
+use super::{top_level_bindings, Binding, Scopes, ValueRef};
+
+/// Module whose default export binds class names to a styles object
+const CLASSNAMES_BIND: &str = "classnames/bind";
+
+/// Functions created by `classNames.bind(styles)`, which take the classes of `styles`
+/// as strings: `cx('button', { active: isActive })`
+#[derive(Default)]
+pub struct ClassHelpers {
+    /// Imports of `classnames/bind`
+    imported: HashSet<Binding>,
+    /// Top-level declarations and imports named like a helper configured by the project
+    configured: HashSet<Binding>,
+    /// Bound helpers with the object they are bound to
+    bound: HashMap<Binding, ValueRef>,
+}
+
+impl ClassHelpers {
+    pub fn new(module: &Module, scopes: &Scopes, names: &[String]) -> Self {
+        let mut helpers = ClassHelpers {
+            configured: top_level_bindings(module)
+                .into_iter()
+                .filter(|binding| names.contains(&binding.name))
+                .collect(),
+            ..Default::default()
+        };
+
+        for item in &module.body {
+            let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item else {
+                continue;
+            };
+            if import.src.value != CLASSNAMES_BIND {
+                continue;
+            }
+            for specifier in &import.specifiers {
+                match specifier {
+                    ImportSpecifier::Default(default) => {
+                        helpers.imported.insert(Binding::declared(&default.local));
+                    }
+                    ImportSpecifier::Namespace(namespace) => {
+                        helpers.imported.insert(Binding::declared(&namespace.local));
+                    }
+                    ImportSpecifier::Named(_) => {}
+                }
+            }
+        }
+
+        let mut collector = BindCollector {
+            helpers: &helpers,
+            scopes,
+            bound: Vec::new(),
+        };
+        module.visit_with(&mut collector);
+        helpers.bound.extend(collector.bound);
+        helpers
+    }
+
+    fn is_helper(&self, expr: &Expr, scopes: &Scopes) -> bool {
+        let Expr::Ident(ident) = expr else {
+            return false;
+        };
+        scopes.binding(ident).is_some_and(|binding| {
+            self.imported.contains(&binding) || self.configured.contains(&binding)
+        })
+    }
+
+    /// The object a helper is bound to by `classNames.bind(object)`
+    pub fn bind_target<'a>(&self, call: &'a CallExpr, scopes: &Scopes) -> Option<&'a Expr> {
+        let Callee::Expr(callee) = &call.callee else {
+            return None;
+        };
+        let Expr::Member(member) = &**callee else {
+            return None;
+        };
+        let is_bind = matches!(&member.prop, MemberProp::Ident(prop) if prop.sym == "bind");
+        if !is_bind || !self.is_helper(&member.obj, scopes) {
+            return None;
+        }
+        call.args.first().map(|arg| &*arg.expr)
+    }
+
+    /// The object `callee` is bound to when it is a bound helper
+    pub fn bound_value(&self, callee: &Expr, scopes: &Scopes) -> Option<&ValueRef> {
+        match callee {
+            Expr::Ident(ident) => self.bound.get(&scopes.binding(ident)?),
+            Expr::Paren(paren) => self.bound_value(&paren.expr, scopes),
+            _ => None,
+        }
+    }
+}
+
+/// `const cx = classNames.bind(styles)`
+struct BindCollector<'a> {
+    helpers: &'a ClassHelpers,
+    scopes: &'a Scopes,
+    bound: Vec<(Binding, ValueRef)>,
+}
+
+impl Visit for BindCollector<'_> {
+    fn visit_var_declarator(&mut self, node: &VarDeclarator) {
+        if let (Pat::Ident(local), Some(Expr::Call(call))) = (&node.name, node.init.as_deref()) {
+            let target = self.helpers.bind_target(call, self.scopes);
+            if let Some(value) = target.and_then(|target| ValueRef::from_expr(target, self.scopes))
+            {
+                self.bound.push((Binding::declared(&local.id), value));
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::parsers::{
+        extract_css_imports, extract_used_classes, ParsedScript, ScriptDialect, StylesRoots,
+        UsageKind, UsedClassName, ValueRef,
+    };
+
+    /// Usages in `code` of the style modules it imports as a whole, sorted by position
+    fn usages(code: &str, helpers: &[&str]) -> Vec<UsedClassName> {
+        let syntax = ScriptDialect::Tsx.syntax(false);
+        let script = ParsedScript::parse(code, "test.tsx".into(), syntax).unwrap();
+        let roots = StylesRoots {
+            values: extract_css_imports(&script)
+                .objects
+                .into_iter()
+                .map(|(path, binding)| (ValueRef::Ident(binding), HashSet::from([path])))
+                .collect(),
+            followed: HashSet::new(),
+        };
+        let helpers: Vec<String> = helpers.iter().map(|name| name.to_string()).collect();
+
+        let mut usages: Vec<_> = extract_used_classes(&script, &roots, &helpers)
+            .into_values()
+            .flatten()
+            .collect();
+        usages.sort_by_key(|used| (used.line, used.column));
+        usages
+    }
+
+    /// Classes used by name, leaving out escapes of the whole object
+    fn names(usages: &[UsedClassName]) -> Vec<&str> {
+        usages
+            .iter()
+            .filter(|used| used.kind != UsageKind::Escape)
+            .map(|used| used.class_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn bound_helper_uses_strings_arrays_and_object_keys() {
+        let usages = usages(
+            "import classNames from 'classnames/bind';
+            import styles from './a.module.css';
+            const cx = classNames.bind(styles);
+            cx('button', ['arr', cond && 'and'], { active: on, 'quoted-key': on }, on ? 'yes' : 'no');",
+            &[],
+        );
+
+        assert_eq!(
+            names(&usages),
+            ["button", "arr", "and", "active", "quoted-key", "yes", "no"]
+        );
+    }
+
+    #[test]
+    fn class_names_are_spanned_inside_the_string_literal() {
+        let usages = usages(
+            "import classNames from 'classnames/bind';
+import styles from './a.module.css';
+const cx = classNames.bind(styles);
+cx('missing');",
+            &[],
+        );
+
+        let used = &usages[0];
+        assert_eq!(used.class_name, "missing");
+        assert_eq!(
+            (used.line, used.column, used.end_line, used.end_column),
+            (4, 4, 4, 11)
+        );
+    }
+
+    /// Binding with anything but a helper hands the object to unknown code
+    #[test]
+    fn other_binders_let_styles_escape() {
+        let usages = usages(
+            "import classNames from 'classnames';
+            import styles from './a.module.css';
+            const cx = classNames.bind(styles);
+            cx('button');",
+            &[],
+        );
+
+        assert_eq!(names(&usages), Vec::<&str>::new());
+        assert_eq!(usages[0].kind, UsageKind::Escape);
+    }
+
+    #[test]
+    fn configured_helpers_are_top_level_bindings() {
+        let usages = usages(
+            "import { bindStyles } from './helpers';
+            import styles from './a.module.css';
+            const cx = bindStyles.bind(styles);
+            cx('imported');
+            function Card(bindStyles) {
+                const local = bindStyles.bind(styles);
+                local('shadowed');
+            }",
+            &["bindStyles"],
+        );
+
+        assert_eq!(names(&usages), ["imported"]);
+    }
+}
@@ -0,0 +1,630 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+use swc_common::Spanned;
+use swc_ecma_ast::{
+    ArrowExpr, BlockStmt, BlockStmtOrExpr, CallExpr, Callee, Decl, DefaultDecl, ExportSpecifier,
+    Expr, Function, ImportSpecifier, JSXAttrName, JSXAttrOrSpread, JSXAttrValue, JSXElementName,
+    JSXExpr, JSXOpeningElement, MemberProp, Module, ModuleDecl, ModuleExportName, ModuleItem,
+    ObjectPatProp, Pat, PropName, ReturnStmt, VarDeclarator,
+};
+use swc_ecma_visit::{Visit, VisitWith};
+
+use super::{is_style_module, Binding, Scopes};
+
+/// Expression that may hold a styles object: `styles` or `props.styles`
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ValueRef {
+    Ident(Binding),
+    Member(Binding, String),
+}
+
+impl ValueRef {
+    /// Reference written by `expr`, `None` for undeclared names
+    pub fn from_expr(expr: &Expr, scopes: &Scopes) -> Option<Self> {
+        match expr {
+            Expr::Ident(ident) => Some(ValueRef::Ident(scopes.binding(ident)?)),
+            Expr::Member(member) => match (&*member.obj, &member.prop) {
+                (Expr::Ident(obj), MemberProp::Ident(prop)) => {
+                    Some(ValueRef::Member(scopes.binding(obj)?, prop.sym.to_string()))
+                }
+                _ => None,
+            },
+            Expr::Paren(paren) => Self::from_expr(&paren.expr, scopes),
+            Expr::TsAs(ts) => Self::from_expr(&ts.expr, scopes),
+            Expr::TsTypeAssertion(ts) => Self::from_expr(&ts.expr, scopes),
+            Expr::TsNonNull(ts) => Self::from_expr(&ts.expr, scopes),
+            Expr::TsSatisfies(ts) => Self::from_expr(&ts.expr, scopes),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValueRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueRef::Ident(binding) => write!(f, "{}", binding.name),
+            ValueRef::Member(object, prop) => write!(f, "{}.{}", object.name, prop),
+        }
+    }
+}
+
+/// References that hold style modules inside a file
+#[derive(Default, Debug)]
+pub struct StylesRoots {
+    /// Paths of the style modules each reference may hold
+    pub values: HashMap<ValueRef, HashSet<String>>,
+    /// Positions of references the flow analysis followed into another binding,
+    /// function or file, so they are not escapes
+    pub followed: HashSet<u32>,
+}
+
+/// Reference to a styles object together with the byte position it is written at,
+/// so that the flow analysis can tell which references it managed to follow
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ValueUse {
+    pub value: ValueRef,
+    pub pos: u32,
+}
+
+impl ValueUse {
+    fn from_expr(expr: &Expr, scopes: &Scopes) -> Option<Self> {
+        Some(ValueUse {
+            value: ValueRef::from_expr(expr, scopes)?,
+            pos: expr.span().lo().0,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ParamBinding {
+    /// `(styles)` or `(props)`
+    Ident(Binding),
+    /// `({ styles, classes: s })` as `(prop, local)` pairs
+    Object(Vec<(String, Binding)>),
+    Other,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct FunctionSummary {
+    pub params: Vec<ParamBinding>,
+    pub returns: Vec<ValueUse>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ArgSlot {
+    /// `useTitle(styles)`
+    Index(usize),
+    /// `<Card styles={styles} />`
+    Prop(String),
+}
+
+/// A possible styles object handed to a component or function
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Pass {
+    pub callee: Binding,
+    pub slot: ArgSlot,
+    pub value: ValueUse,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum BindingSource {
+    /// `const s = styles`, `const { styles } = props`
+    Value(ValueUse),
+    /// `const s = useButtonStyles()`
+    Call(Binding),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScriptImport {
+    pub local: Binding,
+    /// Specifier as written
+    pub specifier: String,
+    /// Imported name, `default` for default imports
+    pub imported: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExportBinding {
+    pub local: Binding,
+    /// Position of the reference for `export default styles` and `export { styles }`
+    pub pos: Option<u32>,
+}
+
+/// What a script module imports, exports, declares and passes around,
+/// the per-file input of the cross-file styles flow analysis
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct ScriptSummary {
+    /// Every non-style import by local name
+    pub imports: HashMap<String, ScriptImport>,
+    /// Exported name, `default` for the default export, with its local binding
+    pub exports: HashMap<String, ExportBinding>,
+    /// `export { a as b } from './script'` by exported name, with the specifier and imported name
+    pub reexports: HashMap<String, (String, String)>,
+    /// Specifiers of `export * from './script'`
+    pub star_reexports: Vec<String>,
+    /// Functions and components by declaration, an anonymous default export is named `default`.
+    /// A list of pairs since the cache can't store maps with structured keys
+    pub functions: Vec<(Binding, FunctionSummary)>,
+    pub bindings: Vec<(Binding, BindingSource)>,
+    pub passes: Vec<Pass>,
+}
+
+impl ScriptSummary {
+    pub fn function(&self, binding: &Binding) -> Option<&FunctionSummary> {
+        self.functions
+            .iter()
+            .find(|(declared, _)| declared == binding)
+            .map(|(_, function)| function)
+    }
+}
+
+fn param_binding(pat: &Pat) -> ParamBinding {
+    match pat {
+        Pat::Ident(ident) => ParamBinding::Ident(Binding::declared(&ident.id)),
+        Pat::Assign(assign) => param_binding(&assign.left),
+        Pat::Object(object) => ParamBinding::Object(
+            object
+                .props
+                .iter()
+                .filter_map(|prop| match prop {
+                    ObjectPatProp::Assign(prop) => {
+                        Some((prop.key.sym.to_string(), Binding::declared(&prop.key)))
+                    }
+                    ObjectPatProp::KeyValue(prop) => {
+                        match (&prop.key, param_binding(&prop.value)) {
+                            (PropName::Ident(key), ParamBinding::Ident(local)) => {
+                                Some((key.sym.to_string(), local))
+                            }
+                            _ => None,
+                        }
+                    }
+                    ObjectPatProp::Rest(_) => None,
+                })
+                .collect(),
+        ),
+        _ => ParamBinding::Other,
+    }
+}
+
+/// Collects the `return` values of a function body without entering nested functions
+struct ReturnCollector<'a> {
+    scopes: &'a Scopes,
+    returns: Vec<ValueUse>,
+}
+
+impl Visit for ReturnCollector<'_> {
+    fn visit_return_stmt(&mut self, node: &ReturnStmt) {
+        if let Some(value) = node
+            .arg
+            .as_deref()
+            .and_then(|arg| ValueUse::from_expr(arg, self.scopes))
+        {
+            self.returns.push(value);
+        }
+    }
+
+    fn visit_function(&mut self, _: &Function) {}
+
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+}
+
+fn collect_returns(body: &BlockStmt, scopes: &Scopes) -> Vec<ValueUse> {
+    let mut collector = ReturnCollector {
+        scopes,
+        returns: Vec::new(),
+    };
+    body.visit_children_with(&mut collector);
+    collector.returns
+}
+
+fn summarize_function(function: &Function, scopes: &Scopes) -> FunctionSummary {
+    FunctionSummary {
+        params: function
+            .params
+            .iter()
+            .map(|p| param_binding(&p.pat))
+            .collect(),
+        returns: function
+            .body
+            .as_ref()
+            .map(|body| collect_returns(body, scopes))
+            .unwrap_or_default(),
+    }
+}
+
+fn summarize_arrow(arrow: &ArrowExpr, scopes: &Scopes) -> FunctionSummary {
+    let returns = match &*arrow.body {
+        BlockStmtOrExpr::BlockStmt(block) => collect_returns(block, scopes),
+        BlockStmtOrExpr::Expr(expr) => ValueUse::from_expr(expr, scopes).into_iter().collect(),
+    };
+
+    FunctionSummary {
+        params: arrow.params.iter().map(param_binding).collect(),
+        returns,
+    }
+}
+
+/// Function defined by an expression, also through wrappers like `memo((props) => ...)`
+fn summarize_function_expr(expr: &Expr, scopes: &Scopes) -> Option<FunctionSummary> {
+    match expr {
+        Expr::Arrow(arrow) => Some(summarize_arrow(arrow, scopes)),
+        Expr::Fn(function) => Some(summarize_function(&function.function, scopes)),
+        Expr::Paren(paren) => summarize_function_expr(&paren.expr, scopes),
+        Expr::Call(call) => call
+            .args
+            .first()
+            .and_then(|arg| summarize_function_expr(&arg.expr, scopes)),
+        _ => None,
+    }
+}
+
+fn export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+        ModuleExportName::Str(str) => str.value.to_string(),
+    }
+}
+
+struct Summarizer<'a> {
+    scopes: &'a Scopes,
+    summary: ScriptSummary,
+}
+
+impl Summarizer<'_> {
+    fn add_export(&mut self, exported: String, local: Binding, pos: Option<u32>) {
+        self.summary
+            .exports
+            .insert(exported, ExportBinding { local, pos });
+    }
+
+    fn summarize_module_decl(&mut self, decl: &ModuleDecl) {
+        match decl {
+            ModuleDecl::Import(import) if !import.type_only => {
+                let src = import.src.value.to_string();
+                if is_style_module(&src) {
+                    return;
+                }
+                for specifier in &import.specifiers {
+                    let (local, imported) = match specifier {
+                        ImportSpecifier::Default(default) => (&default.local, "default".into()),
+                        ImportSpecifier::Named(named) => (
+                            &named.local,
+                            named
+                                .imported
+                                .as_ref()
+                                .map(export_name)
+                                .unwrap_or_else(|| named.local.sym.to_string()),
+                        ),
+                        ImportSpecifier::Namespace(_) => continue,
+                    };
+                    self.summary.imports.insert(
+                        local.sym.to_string(),
+                        ScriptImport {
+                            local: Binding::declared(local),
+                            specifier: src.clone(),
+                            imported,
+                        },
+                    );
+                }
+            }
+            ModuleDecl::ExportDecl(export) => match &export.decl {
+                Decl::Fn(function) => {
+                    let local = Binding::declared(&function.ident);
+                    self.add_export(local.name.clone(), local, None);
+                }
+                Decl::Var(var) => {
+                    for declarator in &var.decls {
+                        if let Pat::Ident(ident) = &declarator.name {
+                            let local = Binding::declared(&ident.id);
+                            self.add_export(local.name.clone(), local, None);
+                        }
+                    }
+                }
+                _ => {}
+            },
+            ModuleDecl::ExportDefaultDecl(export) => {
+                if let DefaultDecl::Fn(function) = &export.decl {
+                    let local = function.ident.as_ref().map_or_else(
+                        || Binding {
+                            name: "default".into(),
+                            pos: export.span.lo.0,
+                        },
+                        Binding::declared,
+                    );
+                    self.summary.functions.push((
+                        local.clone(),
+                        summarize_function(&function.function, self.scopes),
+                    ));
+                    self.add_export("default".into(), local, None);
+                }
+            }
+            ModuleDecl::ExportDefaultExpr(export) => match &*export.expr {
+                Expr::Ident(ident) => {
+                    if let Some(local) = self.scopes.binding(ident) {
+                        self.add_export("default".into(), local, Some(ident.span.lo.0));
+                    }
+                }
+                expr => {
+                    if let Some(function) = summarize_function_expr(expr, self.scopes) {
+                        let local = Binding {
+                            name: "default".into(),
+                            pos: export.span.lo.0,
+                        };
+                        self.summary.functions.push((local.clone(), function));
+                        self.add_export("default".into(), local, None);
+                    }
+                }
+            },
+            ModuleDecl::ExportNamed(export) if export.src.is_some() => {
+                let src = export.src.as_ref().map(|src| src.value.to_string());
+                let Some(src) = src.filter(|src| !is_style_module(src)) else {
+                    return;
+                };
+                for specifier in &export.specifiers {
+                    if let ExportSpecifier::Named(named) = specifier {
+                        let imported = export_name(&named.orig);
+                        let exported = named
+                            .exported
+                            .as_ref()
+                            .map_or_else(|| imported.clone(), export_name);
+                        self.summary
+                            .reexports
+                            .insert(exported, (src.clone(), imported));
+                    }
+                }
+            }
+            ModuleDecl::ExportAll(export) if !is_style_module(&export.src.value) => {
+                self.summary
+                    .star_reexports
+                    .push(export.src.value.to_string());
+            }
+            ModuleDecl::ExportNamed(export) => {
+                for specifier in &export.specifiers {
+                    if let ExportSpecifier::Named(named) = specifier {
+                        let ModuleExportName::Ident(orig) = &named.orig else {
+                            continue;
+                        };
+                        let Some(local) = self.scopes.binding(orig) else {
+                            continue;
+                        };
+                        let exported = named
+                            .exported
+                            .as_ref()
+                            .map_or_else(|| orig.sym.to_string(), export_name);
+                        self.add_export(exported, local, Some(orig.span.lo.0));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visit for Summarizer<'_> {
+    fn visit_module(&mut self, node: &Module) {
+        for item in &node.body {
+            if let ModuleItem::ModuleDecl(decl) = item {
+                self.summarize_module_decl(decl);
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+
+    fn visit_decl(&mut self, node: &Decl) {
+        if let Decl::Fn(function) = node {
+            self.summary.functions.push((
+                Binding::declared(&function.ident),
+                summarize_function(&function.function, self.scopes),
+            ));
+        }
+
+        node.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, node: &VarDeclarator) {
+        if let Some(init) = &node.init {
+            match &node.name {
+                Pat::Ident(ident) => {
+                    let local = Binding::declared(&ident.id);
+                    if let Some(function) = summarize_function_expr(init, self.scopes) {
+                        self.summary.functions.push((local.clone(), function));
+                    }
+
+                    if let Some(value) = ValueUse::from_expr(init, self.scopes) {
+                        self.summary
+                            .bindings
+                            .push((local, BindingSource::Value(value)));
+                    } else if let Expr::Call(CallExpr {
+                        callee: Callee::Expr(callee),
+                        ..
+                    }) = &**init
+                    {
+                        if let Some(callee) = callee.as_ident().and_then(|c| self.scopes.binding(c))
+                        {
+                            self.summary
+                                .bindings
+                                .push((local, BindingSource::Call(callee)));
+                        }
+                    }
+                }
+                // `const { styles } = props`
+                Pat::Object(_) => {
+                    if let (ParamBinding::Object(props), Expr::Ident(object)) =
+                        (param_binding(&node.name), &**init)
+                    {
+                        if let Some(object_binding) = self.scopes.binding(object) {
+                            for (key, local) in props {
+                                let value = ValueUse {
+                                    value: ValueRef::Member(object_binding.clone(), key),
+                                    pos: object.span.lo.0,
+                                };
+                                self.summary
+                                    .bindings
+                                    .push((local, BindingSource::Value(value)));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        if let Callee::Expr(callee) = &node.callee {
+            if let Some(callee) = callee.as_ident().and_then(|c| self.scopes.binding(c)) {
+                for (index, arg) in node.args.iter().enumerate() {
+                    if arg.spread.is_some() {
+                        break;
+                    }
+                    if let Some(value) = ValueUse::from_expr(&arg.expr, self.scopes) {
+                        self.summary.passes.push(Pass {
+                            callee: callee.clone(),
+                            slot: ArgSlot::Index(index),
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+
+    fn visit_jsx_opening_element(&mut self, node: &JSXOpeningElement) {
+        if let Some(callee) = match &node.name {
+            JSXElementName::Ident(callee) => self.scopes.binding(callee),
+            _ => None,
+        } {
+            for attr in &node.attrs {
+                let JSXAttrOrSpread::JSXAttr(attr) = attr else {
+                    continue;
+                };
+                let (JSXAttrName::Ident(name), Some(JSXAttrValue::JSXExprContainer(container))) =
+                    (&attr.name, &attr.value)
+                else {
+                    continue;
+                };
+                let JSXExpr::Expr(expr) = &container.expr else {
+                    continue;
+                };
+
+                if let Some(value) = ValueUse::from_expr(expr, self.scopes) {
+                    self.summary.passes.push(Pass {
+                        callee: callee.clone(),
+                        slot: ArgSlot::Prop(name.sym.to_string()),
+                        value,
+                    });
+                }
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+}
+
+pub fn extract_script_summary(module: &Module, scopes: &Scopes) -> ScriptSummary {
+    let mut summarizer = Summarizer {
+        scopes,
+        summary: ScriptSummary::default(),
+    };
+    module.visit_with(&mut summarizer);
+    summarizer.summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::{ParsedScript, ScriptDialect};
+
+    fn summarize(code: &str) -> ScriptSummary {
+        let syntax = ScriptDialect::Tsx.syntax(false);
+        let script = ParsedScript::parse(code, "test.tsx".into(), syntax).unwrap();
+        extract_script_summary(&script.module, &script.scopes)
+    }
+
+    #[test]
+    fn passes_resolve_to_the_component_in_scope() {
+        let summary = summarize(
+            "import styles from './s.module.css';
+            function Outer() {
+                function Card(props) { return props.styles; }
+                return <Card styles={styles} />;
+            }
+            function Card({ styles: s }) { return s; }
+            export function Page() { return <Card styles={styles} />; }",
+        );
+
+        let mut cards: Vec<_> = summary
+            .functions
+            .iter()
+            .map(|(binding, _)| binding)
+            .filter(|binding| binding.name == "Card")
+            .collect();
+        cards.sort_by_key(|binding| binding.pos);
+        let callees: Vec<_> = summary.passes.iter().map(|pass| &pass.callee).collect();
+
+        assert_eq!(callees, cards);
+    }
+
+    #[test]
+    fn calls_resolve_through_shadowing_parameters() {
+        let summary = summarize(
+            "import styles from './s.module.css';
+            function useStyles() { return styles; }
+            function Card(useStyles) { const s = useStyles(); const t = getStyles(); }",
+        );
+
+        let callees: Vec<_> = summary
+            .bindings
+            .iter()
+            .filter_map(|(_, source)| match source {
+                BindingSource::Call(callee) => Some(callee),
+                BindingSource::Value(_) => None,
+            })
+            .collect();
+
+        assert_eq!(callees.len(), 1);
+        assert!(summary.function(callees[0]).is_none());
+    }
+
+    #[test]
+    fn switch_cases_and_namespaces_shadow_imports() {
+        let summary = summarize(
+            "import styles from './s.module.css';
+            function pick(k) {
+                switch (k) {
+                    default: const styles = { zz: 1 }; return styles;
+                }
+            }
+            namespace N {
+                const styles = { yy: 1 };
+                export function get() { return styles; }
+            }
+            function outer() { return styles; }",
+        );
+
+        let returned = |name: &str| match &summary.functions.iter().find(|(b, _)| b.name == name) {
+            Some((_, function)) => match &function.returns[..] {
+                [ValueUse {
+                    value: ValueRef::Ident(binding),
+                    ..
+                }] => binding.pos,
+                returns => panic!("{:?}", returns),
+            },
+            None => panic!("{} is not summarized", name),
+        };
+        let import = returned("outer");
+
+        assert_ne!(returned("pick"), import);
+        assert_ne!(returned("get"), import);
+        assert_ne!(returned("pick"), returned("get"));
+    }
+}
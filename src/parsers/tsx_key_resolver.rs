@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+
+use swc_ecma_ast::{
+    BinaryOp, Decl, Expr, Lit, MemberProp, Module, ObjectPatProp, Pat, PropName, TsEntityName,
+    TsKeywordTypeKind, TsLit, TsType, TsTypeElement, TsTypeLit, TsTypeRef,
+    TsUnionOrIntersectionType, VarDeclKind,
+};
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// Upper bound for the keys a single expression may resolve to,
+/// `styles[`${a}_${b}`]` multiplies the candidates of every part
+const MAX_KEYS: usize = 64;
+const MAX_DEPTH: usize = 16;
+
+/// Statically known facts about the identifiers of a TSX module that key expressions
+/// like `styles[variant]` can be resolved against
+#[derive(Default)]
+pub struct KeyResolver {
+    /// `const size = 'large'`
+    constants: HashMap<String, Vec<Expr>>,
+    /// Bindings with a type annotation: `(variant: Variant)`, `({ variant }: Props)`
+    typed_bindings: HashMap<String, Vec<TsType>>,
+    /// `type Variant = ...` and `interface Props { ... }`
+    types: HashMap<String, Vec<TsType>>,
+}
+
+impl KeyResolver {
+    pub fn new(module: &Module) -> Self {
+        let mut resolver = Self::default();
+        module.visit_with(&mut resolver);
+        resolver
+    }
+
+    /// Every string `expr` may evaluate to, `None` when any of them is unknown
+    pub fn resolve(&self, expr: &Expr) -> Option<Vec<String>> {
+        self.resolve_expr(expr, 0)
+            .filter(|keys| !keys.is_empty() && keys.len() <= MAX_KEYS)
+    }
+
+    fn resolve_expr(&self, expr: &Expr, depth: usize) -> Option<Vec<String>> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let depth = depth + 1;
+
+        match expr {
+            Expr::Lit(Lit::Str(str)) => Some(vec![str.value.to_string()]),
+            Expr::Tpl(tpl) => {
+                let mut keys = vec![String::new()];
+                for (i, quasi) in tpl.quasis.iter().enumerate() {
+                    let text = quasi.cooked.as_ref().unwrap_or(&quasi.raw);
+                    keys.iter_mut().for_each(|key| key.push_str(text));
+
+                    if let Some(expr) = tpl.exprs.get(i) {
+                        let parts = self.resolve_expr(expr, depth)?;
+                        if keys.len() * parts.len() > MAX_KEYS {
+                            return None;
+                        }
+                        keys = keys
+                            .iter()
+                            .flat_map(|key| parts.iter().map(move |part| format!("{key}{part}")))
+                            .collect();
+                    }
+                }
+                Some(keys)
+            }
+            Expr::Paren(paren) => self.resolve_expr(&paren.expr, depth),
+            Expr::TsAs(ts) => self.resolve_expr(&ts.expr, depth),
+            Expr::TsConstAssertion(ts) => self.resolve_expr(&ts.expr, depth),
+            Expr::TsTypeAssertion(ts) => self.resolve_expr(&ts.expr, depth),
+            Expr::TsSatisfies(ts) => self.resolve_expr(&ts.expr, depth),
+            Expr::TsNonNull(ts) => self.resolve_expr(&ts.expr, depth),
+            Expr::Cond(cond) => {
+                let mut keys = self.resolve_expr(&cond.cons, depth)?;
+                keys.extend(self.resolve_expr(&cond.alt, depth)?);
+                Some(keys)
+            }
+            Expr::Bin(bin) => match bin.op {
+                BinaryOp::LogicalOr | BinaryOp::NullishCoalescing => {
+                    let mut keys = self.resolve_expr(&bin.left, depth)?;
+                    keys.extend(self.resolve_expr(&bin.right, depth)?);
+                    Some(keys)
+                }
+                // A falsy left operand is never a class name
+                BinaryOp::LogicalAnd => self.resolve_expr(&bin.right, depth),
+                _ => None,
+            },
+            Expr::Ident(ident) => {
+                let name = ident.sym.as_str();
+                if let Some(constants) = self.constants.get(name) {
+                    return self.resolve_all(constants, |e| self.resolve_expr(e, depth));
+                }
+                let types = self.typed_bindings.get(name)?;
+                self.resolve_all(types, |t| self.type_literals(t, depth))
+            }
+            // `props.variant` with `props: Props`
+            Expr::Member(member) => {
+                let (Expr::Ident(object), MemberProp::Ident(prop)) = (&*member.obj, &member.prop)
+                else {
+                    return None;
+                };
+                let types = self.typed_bindings.get(object.sym.as_str())?;
+                let members: Vec<TsType> = types
+                    .iter()
+                    .filter_map(|t| self.member_type(t, prop.sym.as_str(), depth))
+                    .collect();
+                if members.is_empty() {
+                    return None;
+                }
+                self.resolve_all(&members, |t| self.type_literals(t, depth))
+            }
+            _ => None,
+        }
+    }
+
+    fn resolve_all<T>(
+        &self,
+        items: &[T],
+        resolve: impl Fn(&T) -> Option<Vec<String>>,
+    ) -> Option<Vec<String>> {
+        let mut keys = Vec::new();
+        for item in items {
+            keys.extend(resolve(item)?);
+        }
+        Some(keys)
+    }
+
+    /// String literals of a type like `'primary' | 'secondary'`, following type aliases
+    fn type_literals(&self, ts_type: &TsType, depth: usize) -> Option<Vec<String>> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let depth = depth + 1;
+
+        match ts_type {
+            TsType::TsLitType(lit) => match &lit.lit {
+                TsLit::Str(str) => Some(vec![str.value.to_string()]),
+                _ => None,
+            },
+            // Optional props are `undefined` when missing, which never selects a class
+            TsType::TsKeywordType(keyword)
+                if matches!(
+                    keyword.kind,
+                    TsKeywordTypeKind::TsUndefinedKeyword | TsKeywordTypeKind::TsNullKeyword
+                ) =>
+            {
+                Some(Vec::new())
+            }
+            TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsUnionType(union)) => {
+                self.resolve_all(&union.types, |t| self.type_literals(t, depth))
+            }
+            TsType::TsParenthesizedType(paren) => self.type_literals(&paren.type_ann, depth),
+            TsType::TsTypeRef(type_ref) => {
+                let types = self.types.get(type_ref_name(type_ref)?)?;
+                self.resolve_all(types, |t| self.type_literals(t, depth))
+            }
+            _ => None,
+        }
+    }
+
+    /// Type of the property `name` of an object type, following type aliases and interfaces
+    fn member_type(&self, ts_type: &TsType, name: &str, depth: usize) -> Option<TsType> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let depth = depth + 1;
+
+        match ts_type {
+            TsType::TsTypeLit(lit) => lit.members.iter().find_map(|member| match member {
+                TsTypeElement::TsPropertySignature(property) => match &*property.key {
+                    Expr::Ident(key) if key.sym == name => property
+                        .type_ann
+                        .as_ref()
+                        .map(|ann| (*ann.type_ann).clone()),
+                    _ => None,
+                },
+                _ => None,
+            }),
+            TsType::TsUnionOrIntersectionType(TsUnionOrIntersectionType::TsIntersectionType(
+                intersection,
+            )) => intersection
+                .types
+                .iter()
+                .find_map(|t| self.member_type(t, name, depth)),
+            TsType::TsParenthesizedType(paren) => self.member_type(&paren.type_ann, name, depth),
+            TsType::TsTypeRef(type_ref) => self
+                .types
+                .get(type_ref_name(type_ref)?)?
+                .iter()
+                .find_map(|t| self.member_type(t, name, depth)),
+            _ => None,
+        }
+    }
+
+    /// Records the type of every binding introduced by a parameter pattern
+    fn bind_typed_pattern(&mut self, pat: &Pat) {
+        match pat {
+            Pat::Ident(ident) => {
+                if let Some(ann) = &ident.type_ann {
+                    self.typed_bindings
+                        .entry(ident.id.sym.to_string())
+                        .or_default()
+                        .push((*ann.type_ann).clone());
+                }
+            }
+            Pat::Assign(assign) => self.bind_typed_pattern(&assign.left),
+            // `({ variant, size: s = 'md' }: Props)`
+            Pat::Object(object) => {
+                let Some(ann) = &object.type_ann else {
+                    return;
+                };
+                for prop in &object.props {
+                    let (key, local) = match prop {
+                        ObjectPatProp::Assign(prop) => {
+                            (prop.key.sym.as_str(), prop.key.sym.as_str())
+                        }
+                        ObjectPatProp::KeyValue(prop) => {
+                            let (PropName::Ident(key), Some(local)) =
+                                (&prop.key, binding_name(&prop.value))
+                            else {
+                                continue;
+                            };
+                            (key.sym.as_str(), local)
+                        }
+                        ObjectPatProp::Rest(_) => continue,
+                    };
+
+                    if let Some(member) = self.member_type(&ann.type_ann, key, 0) {
+                        self.typed_bindings
+                            .entry(local.to_string())
+                            .or_default()
+                            .push(member);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn type_ref_name(type_ref: &TsTypeRef) -> Option<&str> {
+    match &type_ref.type_name {
+        TsEntityName::Ident(ident) => Some(ident.sym.as_str()),
+        TsEntityName::TsQualifiedName(_) => None,
+    }
+}
+
+fn binding_name(pat: &Pat) -> Option<&str> {
+    match pat {
+        Pat::Ident(ident) => Some(ident.id.sym.as_str()),
+        Pat::Assign(assign) => binding_name(&assign.left),
+        _ => None,
+    }
+}
+
+impl Visit for KeyResolver {
+    fn visit_decl(&mut self, node: &Decl) {
+        match node {
+            Decl::TsTypeAlias(alias) => {
+                self.types
+                    .entry(alias.id.sym.to_string())
+                    .or_default()
+                    .push((*alias.type_ann).clone());
+            }
+            Decl::TsInterface(interface) => {
+                let types = self.types.entry(interface.id.sym.to_string()).or_default();
+                types.push(TsType::TsTypeLit(TsTypeLit {
+                    span: interface.body.span,
+                    members: interface.body.body.clone(),
+                }));
+                // `interface Props extends BaseProps`
+                for parent in &interface.extends {
+                    if let Expr::Ident(ident) = &*parent.expr {
+                        types.push(TsType::TsTypeRef(TsTypeRef {
+                            span: parent.span,
+                            type_name: TsEntityName::Ident(ident.clone()),
+                            type_params: None,
+                        }));
+                    }
+                }
+            }
+            Decl::Var(var) => {
+                for declarator in &var.decls {
+                    if let (Pat::Ident(ident), Some(init)) = (&declarator.name, &declarator.init) {
+                        if var.kind == VarDeclKind::Const && ident.type_ann.is_none() {
+                            self.constants
+                                .entry(ident.id.sym.to_string())
+                                .or_default()
+                                .push((**init).clone());
+                            continue;
+                        }
+                    }
+                    self.bind_typed_pattern(&declarator.name);
+                }
+            }
+            _ => {}
+        }
+
+        node.visit_children_with(self);
+    }
+
+    fn visit_param(&mut self, node: &swc_ecma_ast::Param) {
+        self.bind_typed_pattern(&node.pat);
+        node.visit_children_with(self);
+    }
+
+    fn visit_arrow_expr(&mut self, node: &swc_ecma_ast::ArrowExpr) {
+        for param in &node.params {
+            self.bind_typed_pattern(param);
+        }
+        node.visit_children_with(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use swc_ecma_ast::{ModuleItem, Stmt};
+
+    use super::*;
+    use crate::parsers::{
+        extract_css_imports, extract_used_classes, ParsedScript, ScriptDialect, StylesRoots,
+        UsageKind, ValueRef,
+    };
+
+    fn parse(code: &str) -> ParsedScript {
+        let syntax = ScriptDialect::Tsx.syntax(false);
+        ParsedScript::parse(code, "test.tsx".into(), syntax).unwrap()
+    }
+
+    /// Keys the last expression statement of `code` resolves to, sorted
+    fn resolve(code: &str) -> Option<Vec<String>> {
+        let script = parse(code);
+        let Some(ModuleItem::Stmt(Stmt::Expr(stmt))) = script.module.body.last() else {
+            panic!("no trailing expression in {}", code);
+        };
+        let mut keys = KeyResolver::new(&script.module).resolve(&stmt.expr)?;
+        keys.sort();
+        Some(keys)
+    }
+
+    #[test]
+    fn ternaries_resolve_both_branches() {
+        assert_eq!(
+            resolve("isBig ? 'big' : 'small';"),
+            Some(vec!["big".into(), "small".into()])
+        );
+    }
+
+    #[test]
+    fn logical_expressions_skip_falsy_left_operands() {
+        assert_eq!(resolve("isOpen && 'open';"), Some(vec!["open".into()]));
+        assert_eq!(
+            resolve("'primary' || 'fallback';"),
+            Some(vec!["fallback".into(), "primary".into()])
+        );
+        assert_eq!(resolve("isOpen || unknown;"), None);
+    }
+
+    #[test]
+    fn local_constants_are_followed() {
+        assert_eq!(
+            resolve("const size = 'large'; const key = size; key;"),
+            Some(vec!["large".into()])
+        );
+        assert_eq!(resolve("let size = 'large'; size;"), None);
+    }
+
+    #[test]
+    fn string_literal_unions_on_props_resolve() {
+        let code = "type Variant = 'primary' | 'secondary';
+            interface Props { variant: Variant; size: 'sm' | 'lg' }
+            function Button({ variant }: Props, props: Props) {}";
+
+        assert_eq!(
+            resolve(&format!("{} variant;", code)),
+            Some(vec!["primary".into(), "secondary".into()])
+        );
+        assert_eq!(
+            resolve(&format!("{} props.size;", code)),
+            Some(vec!["lg".into(), "sm".into()])
+        );
+    }
+
+    #[test]
+    fn template_prefixes_combine_with_every_part() {
+        assert_eq!(
+            resolve("const Card = (size: 'sm' | 'lg') => null; `card_${size}`;"),
+            Some(vec!["card_lg".into(), "card_sm".into()])
+        );
+        assert_eq!(resolve("`card_${size}`;"), None);
+    }
+
+    #[test]
+    fn unresolved_keys_are_dynamic_access() {
+        let script = parse(
+            "import styles from './a.module.css';
+            function Card(size: string) { return styles[size]; }",
+        );
+        let roots = StylesRoots {
+            values: extract_css_imports(&script)
+                .objects
+                .into_iter()
+                .map(|(path, binding)| (ValueRef::Ident(binding), HashSet::from([path])))
+                .collect(),
+            followed: HashSet::new(),
+        };
+
+        let used: Vec<_> = extract_used_classes(&script, &roots, &[])
+            .into_values()
+            .flatten()
+            .collect();
+        assert_eq!(used.len(), 1);
+        assert_eq!(used[0].class_name, "size");
+        assert_eq!(used[0].kind, UsageKind::Dynamic);
+    }
+}
@@ -1,20 +1,71 @@
-use std::collections::HashSet;
-use swc_common::{sync::Lrc, FileName, SourceMap};
-use swc_ecma_ast::{ImportSpecifier, Module, ModuleDecl};
-use swc_ecma_parser::{Parser, StringInput, Syntax, TsSyntax};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use swc_common::{sync::Lrc, BytePos, FileName, SourceMap, SourceMapper, Span, Spanned};
+use swc_ecma_ast::{
+    AssignExpr, AssignOp, AssignPat, AssignTarget, AssignTargetPat, BinaryOp, CallExpr, Callee,
+    ExportDefaultExpr, ExportNamedSpecifier, ExportSpecifier, Expr, ExprOrSpread, Ident,
+    ImportSpecifier, JSXAttrValue, JSXExpr, JSXExprContainer, Lit, MemberProp, Module, ModuleDecl,
+    ModuleExportName, NamedExport, NewExpr, ObjectPat, ObjectPatProp, Pat, Prop, PropName,
+    PropOrSpread, SpreadElement, Tpl, TsType, TsTypeElement, VarDeclarator,
+};
+use swc_ecma_parser::{EsSyntax, Parser, StringInput, Syntax, TsSyntax};
 use swc_ecma_visit::{Visit, VisitWith};
 
-pub fn module_parser(tsx_code: &str) -> anyhow::Result<(Module, Lrc<SourceMap>)> {
-    let cm: Lrc<SourceMap> = Default::default();
-    let fm = cm.new_source_file(FileName::Custom("input.tsx".into()).into(), tsx_code.into());
+use super::{is_style_module, Binding, ClassHelpers, KeyResolver, Scopes, StylesRoots, ValueRef};
+
+/// Language of a script file, chosen by its extension
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ScriptDialect {
+    /// `.js`, `.jsx`, `.mjs` and `.cjs`, JSX is allowed in all of them like CRA does
+    JavaScript,
+    TypeScript,
+    Tsx,
+    /// `.d.ts` and friends
+    Declaration,
+}
+
+impl ScriptDialect {
+    /// Script extensions in the order extensionless imports are resolved
+    pub const EXTENSIONS: [&'static str; 8] =
+        ["tsx", "ts", "jsx", "js", "mts", "cts", "mjs", "cjs"];
 
-    let syntax = Syntax::Typescript(TsSyntax {
-        tsx: true,
-        decorators: false,
-        dts: false,
-        no_early_errors: false,
-        disallow_ambiguous_jsx_like: false,
-    });
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = path.rsplit_once('.')?.1;
+        if !Self::EXTENSIONS.contains(&extension) {
+            return None;
+        }
+
+        let stem = path.trim_end_matches(extension);
+        Some(match extension {
+            "tsx" => ScriptDialect::Tsx,
+            "ts" | "mts" | "cts" if stem.ends_with(".d.") => ScriptDialect::Declaration,
+            "ts" | "mts" | "cts" => ScriptDialect::TypeScript,
+            _ => ScriptDialect::JavaScript,
+        })
+    }
+
+    pub fn syntax(self, decorators: bool) -> Syntax {
+        match self {
+            ScriptDialect::JavaScript => Syntax::Es(EsSyntax {
+                jsx: true,
+                decorators,
+                ..Default::default()
+            }),
+            ScriptDialect::TypeScript | ScriptDialect::Tsx | ScriptDialect::Declaration => {
+                Syntax::Typescript(TsSyntax {
+                    tsx: self == ScriptDialect::Tsx,
+                    decorators,
+                    dts: self == ScriptDialect::Declaration,
+                    ..Default::default()
+                })
+            }
+        }
+    }
+}
+
+pub fn module_parser(code: &str, syntax: Syntax) -> anyhow::Result<(Module, Lrc<SourceMap>)> {
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(FileName::Anon.into(), code.into());
 
     let mut parser = Parser::new(syntax, StringInput::from(&*fm), None);
 
@@ -24,33 +75,507 @@ pub fn module_parser(tsx_code: &str) -> anyhow::Result<(Module, Lrc<SourceMap>)>
     Ok((parse_res, cm))
 }
 
-#[derive(Eq, PartialEq, Hash, Debug)]
+/// A script parsed once and shared by the import, flow and usage analyses
+pub struct ParsedScript {
+    pub file_name: String,
+    pub module: Module,
+    pub source_map: Lrc<SourceMap>,
+    pub scopes: Scopes,
+}
+
+impl ParsedScript {
+    pub fn parse(code: &str, file_name: String, syntax: Syntax) -> anyhow::Result<Self> {
+        let (module, source_map) = module_parser(code, syntax)?;
+        let scopes = Scopes::new(&module);
+        Ok(ParsedScript {
+            file_name,
+            module,
+            source_map,
+            scopes,
+        })
+    }
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize)]
+pub enum UsageKind {
+    /// `styles.name`, `styles['name']` or a key resolved from constants and types
+    Exact,
+    /// ``styles[`size_${size}`]`` with unknown parts, the literal parts of the template
+    /// in order; every class they match is considered used
+    Pattern(Vec<String>),
+    /// `styles[expr]` whose key could not be resolved, `class_name` holds the expression
+    Dynamic,
+    /// The whole object escapes, e.g. `<Child classes={styles} />` or `Object.keys(styles)`,
+    /// so any of its classes may be used
+    Escape,
+}
+
+/// Usage of a class, lines are one-based, columns zero-based and the end is exclusive
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Serialize, Deserialize)]
 pub struct UsedClassName {
     pub class_name: String,
     pub file_name: String,
     pub line: usize,
     pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub kind: UsageKind,
+}
+
+impl UsedClassName {
+    fn from_span(
+        source_map: &SourceMap,
+        file_name: &str,
+        class_name: String,
+        span: Span,
+        kind: UsageKind,
+    ) -> Self {
+        let (line, column) = char_position(source_map, span.lo());
+        let (end_line, end_column) = char_position(source_map, span.hi());
+        UsedClassName {
+            class_name,
+            file_name: file_name.to_string(),
+            line,
+            column,
+            end_line,
+            end_column,
+            kind,
+        }
+    }
+}
+
+/// One-based line and character column of `pos`, swc itself counts columns in UTF-16 code units
+fn char_position(source_map: &SourceMap, pos: BytePos) -> (usize, usize) {
+    let offset = source_map.lookup_byte_offset(pos);
+    let before = &offset.sf.src[..offset.pos.0 as usize];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        source_map.lookup_char_pos(pos).line,
+        before[line_start..].chars().count(),
+    )
+}
+
+/// Part of `span` between its delimiters, the name inside quotes or brackets
+fn inner_span(span: Span) -> Span {
+    Span::new(span.lo() + BytePos(1), span.hi() - BytePos(1))
+}
+
+impl UsageKind {
+    /// Whether a class named `class_name` is selected by this usage of `used`
+    pub fn matches(&self, used: &str, class_name: &str) -> bool {
+        match self {
+            UsageKind::Exact => used == class_name,
+            UsageKind::Pattern(parts) => {
+                let Some((first, parts)) = parts.split_first() else {
+                    return false;
+                };
+                let Some(mut rest) = class_name.strip_prefix(first.as_str()) else {
+                    return false;
+                };
+                // A single part only fixes the start of the name
+                let Some((last, middle)) = parts.split_last() else {
+                    return true;
+                };
+                for part in middle {
+                    match rest.find(part.as_str()) {
+                        Some(index) => rest = &rest[index + part.len()..],
+                        None => return false,
+                    }
+                }
+                rest.ends_with(last.as_str())
+            }
+            UsageKind::Dynamic => false,
+            UsageKind::Escape => true,
+        }
+    }
 }
 
-struct PropertyFinder {
-    variable_name: String,
-    file_name: String,
-    properties: HashSet<UsedClassName>,
-    source_map: Lrc<SourceMap>,
+struct PropertyFinder<'a> {
+    script: &'a ParsedScript,
+    roots: &'a StylesRoots,
+    /// Usages by path of the style module they belong to
+    properties: HashMap<String, HashSet<UsedClassName>>,
+    keys: KeyResolver,
+    helpers: ClassHelpers,
+}
+
+/// Paths of the style modules a reference may hold
+type StylePaths = HashSet<String>;
+
+fn unparen(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(paren) => unparen(&paren.expr),
+        _ => expr,
+    }
+}
+
+/// Key of a computed member whose value is known statically:
+/// `styles['nav-item']` or ``styles[`nav-item`]``
+fn static_member_key(expr: &Expr) -> Option<(String, Span)> {
+    match expr {
+        Expr::Lit(Lit::Str(str)) => Some((str.value.to_string(), str.span)),
+        Expr::Tpl(tpl) if tpl.exprs.is_empty() => {
+            let quasi = tpl.quasis.first()?;
+            let value = quasi.cooked.as_ref().unwrap_or(&quasi.raw);
+            Some((value.to_string(), tpl.span))
+        }
+        Expr::Paren(paren) => static_member_key(&paren.expr),
+        _ => None,
+    }
+}
+
+impl<'a> PropertyFinder<'a> {
+    fn styles_ref(&self, expr: &Expr) -> Option<(ValueRef, &'a StylePaths)> {
+        let roots = self.roots;
+        let value = ValueRef::from_expr(expr, &self.script.scopes)?;
+        let styles = roots.values.get(&value)?;
+        Some((value, styles))
+    }
+
+    fn styles_of(&self, expr: &Expr) -> Option<&'a StylePaths> {
+        self.styles_ref(expr).map(|(_, styles)| styles)
+    }
+
+    fn is_styles(&self, expr: &Expr) -> bool {
+        self.styles_of(expr).is_some()
+    }
+
+    fn binding_styles(&self, ident: &Ident) -> Option<&'a StylePaths> {
+        let roots = self.roots;
+        let binding = self.script.scopes.binding(ident)?;
+        roots.values.get(&ValueRef::Ident(binding))
+    }
+
+    /// Records a usage of each of `styles` highlighting exactly `span`
+    fn insert(&mut self, styles: &StylePaths, class_name: String, span: Span, kind: UsageKind) {
+        let script = self.script;
+        let used = UsedClassName::from_span(
+            &script.source_map,
+            &script.file_name,
+            class_name,
+            span,
+            kind,
+        );
+        for style_path in styles {
+            self.properties
+                .entry(style_path.clone())
+                .or_default()
+                .insert(used.clone());
+        }
+    }
+
+    /// Records a usage written inside the delimiters of `span`, like `'nav-item'` or `[variant]`
+    fn insert_inside(&mut self, styles: &StylePaths, class_name: String, span: Span) {
+        self.insert(styles, class_name, inner_span(span), UsageKind::Exact);
+    }
+
+    /// Records a usage written exactly at `span`, like the `name` of `styles.name`
+    fn insert_at(&mut self, styles: &StylePaths, class_name: String, span: Span) {
+        self.insert(styles, class_name, span, UsageKind::Exact);
+    }
+
+    /// Records the usages of a computed key `styles[expr]`, `span` covers the brackets
+    fn insert_computed_key(&mut self, styles: &StylePaths, expr: &Expr, span: Span) {
+        if let Some((class_name, span)) = static_member_key(expr) {
+            self.insert_inside(styles, class_name, span);
+            return;
+        }
+        if let Some(keys) = self.keys.resolve(expr) {
+            for class_name in keys {
+                self.insert_inside(styles, class_name, span);
+            }
+            return;
+        }
+
+        if let Expr::Tpl(tpl) = unparen(expr) {
+            return self.insert_template(styles, tpl, inner_span(span));
+        }
+
+        let source = self
+            .script
+            .source_map
+            .span_to_snippet(expr.span())
+            .unwrap_or_default();
+        let source = source.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.insert(styles, source, inner_span(span), UsageKind::Dynamic);
+    }
+
+    /// Records every class matching the literal parts of a template with unknown parts
+    fn insert_template(&mut self, styles: &StylePaths, tpl: &Tpl, span: Span) {
+        let parts: Vec<String> = tpl
+            .quasis
+            .iter()
+            .map(|quasi| quasi.cooked.as_ref().unwrap_or(&quasi.raw).to_string())
+            .collect();
+        self.insert(styles, parts.join("*"), span, UsageKind::Pattern(parts));
+    }
+
+    /// Class names passed to a bound helper as strings, array elements and object keys
+    fn insert_class_args(&mut self, styles: &StylePaths, expr: &Expr) {
+        match expr {
+            Expr::Array(array) => {
+                for elem in array.elems.iter().flatten() {
+                    self.insert_class_args(styles, &elem.expr);
+                }
+            }
+            Expr::Object(object) => {
+                for prop in &object.props {
+                    let PropOrSpread::Prop(prop) = prop else {
+                        continue;
+                    };
+                    match &**prop {
+                        Prop::KeyValue(prop) => match &prop.key {
+                            PropName::Ident(key) => {
+                                self.insert_at(styles, key.sym.to_string(), key.span)
+                            }
+                            PropName::Str(key) => {
+                                self.insert_inside(styles, key.value.to_string(), key.span)
+                            }
+                            PropName::Computed(key) => self.insert_class_name(styles, &key.expr),
+                            _ => {}
+                        },
+                        Prop::Shorthand(ident) => {
+                            self.insert_at(styles, ident.sym.to_string(), ident.span)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Expr::Cond(cond) => {
+                self.insert_class_args(styles, &cond.cons);
+                self.insert_class_args(styles, &cond.alt);
+            }
+            Expr::Bin(bin) => match bin.op {
+                // A falsy left operand is never a class name
+                BinaryOp::LogicalAnd => self.insert_class_args(styles, &bin.right),
+                BinaryOp::LogicalOr | BinaryOp::NullishCoalescing => {
+                    self.insert_class_args(styles, &bin.left);
+                    self.insert_class_args(styles, &bin.right);
+                }
+                _ => {}
+            },
+            Expr::Paren(paren) => self.insert_class_args(styles, &paren.expr),
+            _ => self.insert_class_name(styles, expr),
+        }
+    }
+
+    /// A string class name known statically or through constants and types,
+    /// other expressions may hold classes from anywhere and are skipped
+    fn insert_class_name(&mut self, styles: &StylePaths, expr: &Expr) {
+        if let Some((class_name, span)) = static_member_key(expr) {
+            return self.insert_inside(styles, class_name, span);
+        }
+        if let Some(keys) = self.keys.resolve(expr) {
+            for class_name in keys {
+                self.insert_at(styles, class_name, expr.span());
+            }
+            return;
+        }
+        if let Expr::Tpl(tpl) = unparen(expr) {
+            self.insert_template(styles, tpl, inner_span(tpl.span));
+        }
+    }
+
+    fn insert_escape(&mut self, styles: &StylePaths, name: String, span: Span) {
+        if !self.roots.followed.contains(&span.lo().0) {
+            self.insert(styles, name, span, UsageKind::Escape);
+        }
+    }
+
+    /// Lets the styles object escape when `expr` is a reference to it,
+    /// any other expression is searched for usages as usual
+    fn visit_escape_site(&mut self, expr: &Expr) {
+        match self.styles_ref(expr) {
+            Some((value, styles)) => self.insert_escape(styles, value.to_string(), expr.span()),
+            None => expr.visit_with(self),
+        }
+    }
+
+    /// Every key of `const { header, footer: f, ['nav-item']: nav } = styles` is a usage,
+    /// while `...rest` takes the remaining object along
+    fn insert_pattern_keys(&mut self, styles: &StylePaths, pattern: &ObjectPat) {
+        for prop in &pattern.props {
+            match prop {
+                ObjectPatProp::KeyValue(prop) => match &prop.key {
+                    PropName::Ident(key) => self.insert_at(styles, key.sym.to_string(), key.span),
+                    PropName::Str(key) => {
+                        self.insert_inside(styles, key.value.to_string(), key.span)
+                    }
+                    PropName::Computed(key) => {
+                        self.insert_computed_key(styles, &key.expr, key.span)
+                    }
+                    _ => {}
+                },
+                ObjectPatProp::Assign(prop) => {
+                    self.insert_at(styles, prop.key.sym.to_string(), prop.key.span)
+                }
+                ObjectPatProp::Rest(rest) => {
+                    self.insert_escape(styles, "...".into(), rest.dot3_token)
+                }
+            }
+        }
+    }
 }
 
-impl Visit for PropertyFinder {
+impl Visit for PropertyFinder<'_> {
+    /// References outside of the escape sites below, like `if (styles)`, `styles === other`
+    /// or `typeof styles`, only inspect the object and use none of its classes
+    fn visit_expr(&mut self, node: &Expr) {
+        if !self.is_styles(node) {
+            node.visit_children_with(self);
+        }
+    }
+
+    /// `<Child classes={styles} />`
+    fn visit_jsx_attr_value(&mut self, node: &JSXAttrValue) {
+        match node {
+            JSXAttrValue::JSXExprContainer(JSXExprContainer {
+                expr: JSXExpr::Expr(expr),
+                ..
+            }) => self.visit_escape_site(expr),
+            _ => node.visit_children_with(self),
+        }
+    }
+
+    /// `{...styles}` in objects and JSX attributes
+    fn visit_spread_element(&mut self, node: &SpreadElement) {
+        self.visit_escape_site(&node.expr);
+    }
+
+    /// `[...styles]` and `f(...styles)`
+    fn visit_expr_or_spread(&mut self, node: &ExprOrSpread) {
+        match node.spread {
+            Some(_) => self.visit_escape_site(&node.expr),
+            None => node.expr.visit_with(self),
+        }
+    }
+
+    /// `export default styles`
+    fn visit_export_default_expr(&mut self, node: &ExportDefaultExpr) {
+        self.visit_escape_site(&node.expr);
+    }
+
+    fn visit_new_expr(&mut self, node: &NewExpr) {
+        node.callee.visit_with(self);
+        for arg in node.args.iter().flatten() {
+            self.visit_escape_site(&arg.expr);
+        }
+    }
+
+    /// Types never read the styles object, and keys of type literals are not references
+    fn visit_ts_type(&mut self, _: &TsType) {}
+
+    fn visit_ts_type_element(&mut self, _: &TsTypeElement) {}
+
+    /// `classNames.bind(styles)` hands the classes to a helper,
+    /// `cx('button', { active })` uses them, and any other argument like
+    /// `Object.keys(styles)` lets the styles object escape
+    fn visit_call_expr(&mut self, node: &CallExpr) {
+        let scopes = &self.script.scopes;
+        if let Some(target) = self.helpers.bind_target(node, scopes) {
+            if self.is_styles(target) {
+                return;
+            }
+        }
+        if let Callee::Expr(callee) = &node.callee {
+            let roots = self.roots;
+            let bound = self.helpers.bound_value(callee, scopes);
+            if let Some(styles) = bound.and_then(|value| roots.values.get(value)) {
+                for arg in &node.args {
+                    self.insert_class_args(styles, &arg.expr);
+                }
+            }
+        }
+
+        node.callee.visit_with(self);
+        for arg in &node.args {
+            self.visit_escape_site(&arg.expr);
+        }
+    }
+
+    /// `{ styles }` and `{ classes: styles }`
+    fn visit_prop(&mut self, node: &Prop) {
+        match node {
+            Prop::Shorthand(ident) => {
+                if let Some(styles) = self.binding_styles(ident) {
+                    return self.insert_escape(styles, ident.sym.to_string(), ident.span);
+                }
+            }
+            Prop::KeyValue(prop) => {
+                prop.key.visit_with(self);
+                return self.visit_escape_site(&prop.value);
+            }
+            _ => {}
+        }
+        node.visit_children_with(self);
+    }
+
+    /// `export { styles }`
+    fn visit_named_export(&mut self, node: &NamedExport) {
+        if node.src.is_some() {
+            return;
+        }
+        for specifier in &node.specifiers {
+            if let ExportSpecifier::Named(ExportNamedSpecifier {
+                orig: ModuleExportName::Ident(orig),
+                ..
+            }) = specifier
+            {
+                if let Some(styles) = self.binding_styles(orig) {
+                    self.insert_escape(styles, orig.sym.to_string(), orig.span);
+                }
+            }
+        }
+    }
+
     fn visit_member_expr(&mut self, node: &swc_ecma_ast::MemberExpr) {
-        if let swc_ecma_ast::Expr::Ident(ref obj) = *node.obj {
-            if obj.sym == self.variable_name {
-                if let swc_ecma_ast::MemberProp::Ident(ref prop) = node.prop {
-                    let loc = self.source_map.lookup_char_pos(node.span.lo());
-                    self.properties.insert(UsedClassName {
-                        class_name: prop.sym.to_string(),
-                        file_name: self.file_name.clone(),
-                        line: loc.line,
-                        column: self.variable_name.len() + loc.col.0,
-                    });
+        if let Some(styles) = self.styles_of(&node.obj) {
+            match node.prop {
+                swc_ecma_ast::MemberProp::Ident(ref prop) => {
+                    self.insert_at(styles, prop.sym.to_string(), prop.span)
+                }
+                swc_ecma_ast::MemberProp::Computed(ref computed) => {
+                    self.insert_computed_key(styles, &computed.expr, computed.span);
+                    computed.expr.visit_with(self);
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        node.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, node: &VarDeclarator) {
+        if let (Pat::Object(pattern), Some(init)) = (&node.name, &node.init) {
+            if let Some(styles) = self.styles_of(init) {
+                self.insert_pattern_keys(styles, pattern);
+                return pattern.visit_with(self);
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+
+    /// `function Card({ header } = styles)` and nested `{ classes: { header } = styles }`
+    fn visit_assign_pat(&mut self, node: &AssignPat) {
+        if let Pat::Object(pattern) = &*node.left {
+            if let Some(styles) = self.styles_of(&node.right) {
+                self.insert_pattern_keys(styles, pattern);
+                return pattern.visit_with(self);
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+
+    /// `({ header } = styles);`
+    fn visit_assign_expr(&mut self, node: &AssignExpr) {
+        if let AssignTarget::Pat(AssignTargetPat::Object(pattern)) = &node.left {
+            if node.op == AssignOp::Assign {
+                if let Some(styles) = self.styles_of(&node.right) {
+                    self.insert_pattern_keys(styles, pattern);
+                    return pattern.visit_with(self);
                 }
             }
         }
@@ -59,41 +584,154 @@ impl Visit for PropertyFinder {
     }
 }
 
+/// Usages of every style module held by `roots` in a single file, by style module path
 pub fn extract_used_classes(
-    tsx_code: &str,
-    variable_name: &str,
-    file_name: String,
-) -> anyhow::Result<HashSet<UsedClassName>> {
-    let (module, source_map) = module_parser(tsx_code)?;
-
+    script: &ParsedScript,
+    roots: &StylesRoots,
+    class_helpers: &[String],
+) -> HashMap<String, HashSet<UsedClassName>> {
     let mut finder = PropertyFinder {
-        variable_name: variable_name.to_string(),
-        file_name,
-        properties: HashSet::new(),
-        source_map: source_map.clone(),
+        script,
+        roots,
+        properties: HashMap::new(),
+        keys: KeyResolver::new(&script.module),
+        helpers: ClassHelpers::new(&script.module, &script.scopes, class_helpers),
     };
 
-    module.visit_with(&mut finder);
+    script.module.visit_with(&mut finder);
 
-    Ok(finder.properties)
+    finder.properties
 }
 
-struct DefaultCssImportFinder {
-    imported_variables: HashSet<(String, String)>,
+/// Style module imports of a single file
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct CssImports {
+    /// `(path, variable)` bindings of the whole module object:
+    /// `import styles from`, `import * as styles from` and `import { default as styles } from`
+    pub objects: HashSet<(String, Binding)>,
+    /// Classes imported by name, `import { card, title as t } from`, positioned at the specifier
+    pub named: Vec<(String, UsedClassName)>,
+    /// `(path, exported name)` of the module object re-exported with
+    /// `export { default as styles } from` or `export * as styles from`
+    pub reexports: Vec<(String, String)>,
 }
 
-impl Visit for DefaultCssImportFinder {
-    fn visit_module(&mut self, node: &Module) {
-        for stmt in &node.body {
-            if let swc_ecma_ast::ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = stmt {
-                if import.src.value.ends_with(".module.css") {
-                    for specifier in &import.specifiers {
-                        if let ImportSpecifier::Default(default) = specifier {
-                            self.imported_variables.insert((
-                                import.src.value.to_string(),
-                                default.local.sym.to_string(),
-                            ));
-                        }
+struct CssImportFinder<'a> {
+    script: &'a ParsedScript,
+    imports: CssImports,
+}
+
+/// Style module loaded by `require('./a.module.css')`, `await import('./a.module.css')`
+/// or their `.default`
+fn required_style_module(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Call(call) => {
+            let is_loader = match &call.callee {
+                Callee::Import(_) => true,
+                Callee::Expr(callee) => matches!(&**callee, Expr::Ident(i) if i.sym == "require"),
+                Callee::Super(_) => false,
+            };
+            let Some(Expr::Lit(Lit::Str(src))) = call.args.first().map(|arg| &*arg.expr) else {
+                return None;
+            };
+            (is_loader && is_style_module(&src.value)).then(|| src.value.to_string())
+        }
+        Expr::Member(member) => match &member.prop {
+            MemberProp::Ident(prop) if prop.sym == "default" => required_style_module(&member.obj),
+            _ => None,
+        },
+        Expr::Await(await_expr) => required_style_module(&await_expr.arg),
+        Expr::Paren(paren) => required_style_module(&paren.expr),
+        Expr::TsAs(ts) => required_style_module(&ts.expr),
+        Expr::TsNonNull(ts) => required_style_module(&ts.expr),
+        _ => None,
+    }
+}
+
+impl CssImportFinder<'_> {
+    /// `export { default as styles, card } from './a.module.css'`, re-exported classes are used
+    fn insert_reexports(&mut self, export: &NamedExport) {
+        let Some(src) = export
+            .src
+            .as_ref()
+            .filter(|src| is_style_module(&src.value))
+        else {
+            return;
+        };
+        if export.type_only {
+            return;
+        }
+
+        let path = src.value.to_string();
+        for specifier in &export.specifiers {
+            match specifier {
+                ExportSpecifier::Namespace(namespace) => {
+                    let exported = match &namespace.name {
+                        ModuleExportName::Ident(ident) => ident.sym.to_string(),
+                        ModuleExportName::Str(str) => str.value.to_string(),
+                    };
+                    self.imports.reexports.push((path.clone(), exported));
+                }
+                ExportSpecifier::Named(named) => {
+                    let (orig, span) = match &named.orig {
+                        ModuleExportName::Ident(ident) => (ident.sym.to_string(), ident.span),
+                        ModuleExportName::Str(str) => (str.value.to_string(), inner_span(str.span)),
+                    };
+                    if orig != "default" {
+                        self.insert_named(&path, orig, span);
+                        continue;
+                    }
+                    let exported = match &named.exported {
+                        Some(ModuleExportName::Ident(ident)) => ident.sym.to_string(),
+                        Some(ModuleExportName::Str(str)) => str.value.to_string(),
+                        None => orig,
+                    };
+                    self.imports.reexports.push((path.clone(), exported));
+                }
+                ExportSpecifier::Default(_) => {}
+            }
+        }
+    }
+
+    fn insert_named(&mut self, path: &str, class_name: String, span: Span) {
+        let used = UsedClassName::from_span(
+            &self.script.source_map,
+            &self.script.file_name,
+            class_name,
+            span,
+            UsageKind::Exact,
+        );
+        self.imports.named.push((path.to_string(), used));
+    }
+
+    fn insert_object(&mut self, path: &str, local: &Ident) {
+        self.imports
+            .objects
+            .insert((path.to_string(), Binding::declared(local)));
+    }
+
+    /// `const { card, title: t, default: styles, ...rest } = require('./a.module.css')`
+    fn insert_pattern(&mut self, path: &str, pattern: &ObjectPat) {
+        for prop in &pattern.props {
+            match prop {
+                ObjectPatProp::KeyValue(prop) => match (&prop.key, &*prop.value) {
+                    (PropName::Ident(key), Pat::Ident(local)) if key.sym == "default" => {
+                        self.insert_object(path, &local.id)
+                    }
+                    (PropName::Ident(key), _) => {
+                        self.insert_named(path, key.sym.to_string(), key.span)
+                    }
+                    (PropName::Str(key), _) => {
+                        self.insert_named(path, key.value.to_string(), inner_span(key.span))
+                    }
+                    _ => {}
+                },
+                ObjectPatProp::Assign(prop) => {
+                    self.insert_named(path, prop.key.sym.to_string(), prop.key.span)
+                }
+                ObjectPatProp::Rest(rest) => {
+                    if let Pat::Ident(local) = &*rest.arg {
+                        self.insert_object(path, &local.id);
                     }
                 }
             }
@@ -101,14 +739,165 @@ impl Visit for DefaultCssImportFinder {
     }
 }
 
-pub fn extract_default_css_imports(tsx_code: &str) -> anyhow::Result<HashSet<(String, String)>> {
-    let (module, _) = module_parser(tsx_code)?;
+impl Visit for CssImportFinder<'_> {
+    fn visit_module(&mut self, node: &Module) {
+        for stmt in &node.body {
+            let swc_ecma_ast::ModuleItem::ModuleDecl(decl) = stmt else {
+                continue;
+            };
+            if let ModuleDecl::ExportNamed(export) = decl {
+                self.insert_reexports(export);
+            }
+            let ModuleDecl::Import(import) = decl else {
+                continue;
+            };
+            if import.type_only || !is_style_module(&import.src.value) {
+                continue;
+            }
+
+            let path = import.src.value.to_string();
+            for specifier in &import.specifiers {
+                let local = match specifier {
+                    ImportSpecifier::Default(default) => &default.local,
+                    ImportSpecifier::Namespace(namespace) => &namespace.local,
+                    ImportSpecifier::Named(named) if named.is_type_only => continue,
+                    ImportSpecifier::Named(named) => match &named.imported {
+                        Some(ModuleExportName::Ident(i)) if i.sym == "default" => &named.local,
+                        Some(ModuleExportName::Ident(imported)) => {
+                            self.insert_named(&path, imported.sym.to_string(), imported.span);
+                            continue;
+                        }
+                        Some(ModuleExportName::Str(imported)) => {
+                            let span = inner_span(imported.span);
+                            self.insert_named(&path, imported.value.to_string(), span);
+                            continue;
+                        }
+                        None => {
+                            let local = &named.local;
+                            self.insert_named(&path, local.sym.to_string(), local.span);
+                            continue;
+                        }
+                    },
+                };
+                self.insert_object(&path, local);
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+
+    /// `const styles = require('./a.module.css')` and `const styles = (await import('./a.module.css')).default`
+    fn visit_var_declarator(&mut self, node: &VarDeclarator) {
+        if let Some(path) = node.init.as_deref().and_then(required_style_module) {
+            match &node.name {
+                Pat::Ident(local) => self.insert_object(&path, &local.id),
+                Pat::Object(pattern) => self.insert_pattern(&path, pattern),
+                _ => {}
+            }
+        }
+
+        node.visit_children_with(self);
+    }
+}
 
-    let mut finder = DefaultCssImportFinder {
-        imported_variables: HashSet::new(),
+pub fn extract_css_imports(script: &ParsedScript) -> CssImports {
+    let mut finder = CssImportFinder {
+        script,
+        imports: CssImports::default(),
     };
 
-    module.visit_with(&mut finder);
+    script.module.visit_with(&mut finder);
+
+    finder.imports
+}
 
-    Ok(finder.imported_variables)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Usages in `code` of the style modules it imports as a whole, sorted by name
+    fn usages(code: &str) -> Vec<(String, UsageKind)> {
+        let syntax = ScriptDialect::Tsx.syntax(false);
+        let script = ParsedScript::parse(code, "test.tsx".into(), syntax).unwrap();
+        let roots = StylesRoots {
+            values: extract_css_imports(&script)
+                .objects
+                .into_iter()
+                .map(|(path, binding)| (ValueRef::Ident(binding), HashSet::from([path])))
+                .collect(),
+            followed: HashSet::new(),
+        };
+
+        let mut usages: Vec<_> = extract_used_classes(&script, &roots, &[])
+            .into_values()
+            .flatten()
+            .map(|used| (used.class_name, used.kind))
+            .collect();
+        usages.sort_by(|a, b| a.0.cmp(&b.0));
+        usages
+    }
+
+    fn escapes(code: &str) -> bool {
+        usages(code)
+            .iter()
+            .any(|(_, kind)| *kind == UsageKind::Escape)
+    }
+
+    #[test]
+    fn styles_escape_at_escape_sites() {
+        let import = "import styles from './a.module.css';";
+        for site in [
+            "const el = <Child classes={styles} />;",
+            "const el = <div {...styles} />;",
+            "const merged = { ...styles };",
+            "const keys = Object.keys(styles);",
+            "Object.entries(styles).forEach(() => {});",
+            "const props = { classes: styles };",
+            "export default styles;",
+        ] {
+            assert!(escapes(&format!("{}\n{}", import, site)), "{}", site);
+        }
+    }
+
+    #[test]
+    fn inspecting_styles_is_no_escape() {
+        let import = "import styles from './a.module.css';";
+        for site in [
+            "if (styles) { use(styles.b); }",
+            "const same = styles === other && styles.b;",
+            "const kind = typeof styles === 'object' ? styles.b : '';",
+            "const b = styles ? styles.b : '';",
+            "const b = !styles && styles.b;",
+        ] {
+            let code = format!("{}\n{}", import, site);
+            assert_eq!(usages(&code), [("b".into(), UsageKind::Exact)], "{}", site);
+        }
+    }
+
+    fn pattern(parts: &[&str]) -> UsageKind {
+        UsageKind::Pattern(parts.iter().map(|part| part.to_string()).collect())
+    }
+
+    #[test]
+    fn single_part_pattern_matches_by_prefix() {
+        let kind = pattern(&["size_"]);
+
+        assert!(kind.matches("size_*", "size_small"));
+        assert!(kind.matches("size_*", "size_"));
+        assert!(!kind.matches("size_*", "color_red"));
+    }
+
+    #[test]
+    fn pattern_matches_parts_in_order() {
+        let kind = pattern(&["btn_", "_", "--active"]);
+
+        assert!(kind.matches("btn_*_*--active", "btn_primary_large--active"));
+        assert!(!kind.matches("btn_*_*--active", "btn_primary--active"));
+        assert!(!kind.matches("btn_*_*--active", "btn_primary_large"));
+    }
+
+    #[test]
+    fn empty_pattern_matches_nothing() {
+        assert!(!pattern(&[]).matches("", "card"));
+    }
 }
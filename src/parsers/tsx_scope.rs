@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use swc_ecma_ast::{
+    ArrowExpr, BlockStmt, BlockStmtOrExpr, CatchClause, Class, ClassExpr, Constructor, Decl,
+    DefaultDecl, FnExpr, ForHead, ForInStmt, ForOfStmt, ForStmt, Function, Ident, ImportSpecifier,
+    Module, ModuleDecl, ModuleItem, ObjectPatProp, ParamOrTsParamProp, Pat, Stmt, SwitchStmt,
+    TsModuleBlock, TsParamPropParam, VarDecl, VarDeclKind, VarDeclOrExpr,
+};
+use swc_ecma_visit::{Visit, VisitWith};
+
+/// A declared name, identified by the position of its declaring identifier
+/// so that shadowing declarations of the same name stay apart
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Binding {
+    pub name: String,
+    pub pos: u32,
+}
+
+impl Binding {
+    /// Binding introduced by the declaring identifier `ident`
+    pub fn declared(ident: &Ident) -> Self {
+        Binding {
+            name: ident.sym.to_string(),
+            pos: ident.span.lo.0,
+        }
+    }
+}
+
+/// Binding every identifier of a module refers to, following the lexical scopes
+/// of functions, blocks, loops and catch clauses
+#[derive(Default, Debug)]
+pub struct Scopes {
+    /// Position of each identifier to the position of its declaration,
+    /// globals and undeclared names are missing
+    resolved: HashMap<u32, u32>,
+}
+
+impl Scopes {
+    pub fn new(module: &Module) -> Self {
+        let mut resolver = ScopeResolver::default();
+        module.visit_with(&mut resolver);
+        resolver.scopes
+    }
+
+    pub fn binding(&self, ident: &Ident) -> Option<Binding> {
+        self.resolved.get(&ident.span.lo.0).map(|pos| Binding {
+            name: ident.sym.to_string(),
+            pos: *pos,
+        })
+    }
+}
+
+fn pat_bindings(pat: &Pat, bindings: &mut Vec<Binding>) {
+    match pat {
+        Pat::Ident(ident) => bindings.push(Binding::declared(&ident.id)),
+        Pat::Array(array) => array
+            .elems
+            .iter()
+            .flatten()
+            .for_each(|elem| pat_bindings(elem, bindings)),
+        Pat::Object(object) => {
+            for prop in &object.props {
+                match prop {
+                    ObjectPatProp::KeyValue(prop) => pat_bindings(&prop.value, bindings),
+                    ObjectPatProp::Assign(prop) => bindings.push(Binding::declared(&prop.key.id)),
+                    ObjectPatProp::Rest(rest) => pat_bindings(&rest.arg, bindings),
+                }
+            }
+        }
+        Pat::Rest(rest) => pat_bindings(&rest.arg, bindings),
+        Pat::Assign(assign) => pat_bindings(&assign.left, bindings),
+        _ => {}
+    }
+}
+
+/// Names a declaration binds in its own block, `var` declarations are hoisted separately
+fn lexical_bindings(decl: &Decl, bindings: &mut Vec<Binding>) {
+    match decl {
+        Decl::Fn(function) => bindings.push(Binding::declared(&function.ident)),
+        Decl::Class(class) => bindings.push(Binding::declared(&class.ident)),
+        Decl::Var(var) if var.kind != VarDeclKind::Var => var
+            .decls
+            .iter()
+            .for_each(|declarator| pat_bindings(&declarator.name, bindings)),
+        Decl::TsEnum(ts_enum) => bindings.push(Binding::declared(&ts_enum.id)),
+        _ => {}
+    }
+}
+
+fn stmt_bindings(stmts: &[Stmt], bindings: &mut Vec<Binding>) {
+    for stmt in stmts {
+        if let Stmt::Decl(decl) = stmt {
+            lexical_bindings(decl, bindings);
+        }
+    }
+}
+
+/// `var` declarations of a function body or module, without entering nested functions
+#[derive(Default)]
+struct VarCollector {
+    bindings: Vec<Binding>,
+}
+
+impl VarCollector {
+    fn collect<T: VisitWith<Self>>(node: &T) -> Vec<Binding> {
+        let mut collector = Self::default();
+        node.visit_with(&mut collector);
+        collector.bindings
+    }
+}
+
+impl Visit for VarCollector {
+    fn visit_var_decl(&mut self, node: &VarDecl) {
+        if node.kind == VarDeclKind::Var {
+            for declarator in &node.decls {
+                pat_bindings(&declarator.name, &mut self.bindings);
+            }
+        }
+    }
+
+    fn visit_function(&mut self, _: &Function) {}
+
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+
+    fn visit_class(&mut self, _: &Class) {}
+
+    fn visit_ts_module_block(&mut self, _: &TsModuleBlock) {}
+}
+
+/// Names declared or imported at the top level of `module`
+pub fn top_level_bindings(module: &Module) -> Vec<Binding> {
+    let mut bindings = VarCollector::collect(module);
+    module_bindings(&module.body, &mut bindings);
+    bindings
+}
+
+/// Names the items of a module or namespace body bind in it
+fn module_bindings(items: &[ModuleItem], bindings: &mut Vec<Binding>) {
+    for item in items {
+        match item {
+            ModuleItem::Stmt(Stmt::Decl(decl)) => lexical_bindings(decl, bindings),
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(export)) => {
+                lexical_bindings(&export.decl, bindings)
+            }
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(export)) => match &export.decl {
+                DefaultDecl::Fn(FnExpr {
+                    ident: Some(ident), ..
+                })
+                | DefaultDecl::Class(ClassExpr {
+                    ident: Some(ident), ..
+                }) => bindings.push(Binding::declared(ident)),
+                _ => {}
+            },
+            ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => {
+                bindings.extend(import.specifiers.iter().map(|specifier| match specifier {
+                    ImportSpecifier::Default(default) => Binding::declared(&default.local),
+                    ImportSpecifier::Named(named) => Binding::declared(&named.local),
+                    ImportSpecifier::Namespace(namespace) => Binding::declared(&namespace.local),
+                }))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Default)]
+struct ScopeResolver {
+    stack: Vec<HashMap<String, u32>>,
+    scopes: Scopes,
+}
+
+impl ScopeResolver {
+    fn with_scope(&mut self, bindings: Vec<Binding>, f: impl FnOnce(&mut Self)) {
+        let scope = bindings.into_iter().map(|b| (b.name, b.pos)).collect();
+        self.stack.push(scope);
+        f(self);
+        self.stack.pop();
+    }
+}
+
+impl Visit for ScopeResolver {
+    fn visit_ident(&mut self, node: &Ident) {
+        let name = node.sym.as_str();
+        if let Some(pos) = self.stack.iter().rev().find_map(|scope| scope.get(name)) {
+            self.scopes.resolved.insert(node.span.lo.0, *pos);
+        }
+    }
+
+    fn visit_module(&mut self, node: &Module) {
+        let bindings = top_level_bindings(node);
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    /// `namespace Name { ... }`, compiled to a function so its `var` declarations stay inside
+    fn visit_ts_module_block(&mut self, node: &TsModuleBlock) {
+        let mut bindings = VarCollector::collect(&node.body);
+        module_bindings(&node.body, &mut bindings);
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_block_stmt(&mut self, node: &BlockStmt) {
+        let mut bindings = Vec::new();
+        stmt_bindings(&node.stmts, &mut bindings);
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    /// The cases of a switch share one block
+    fn visit_switch_stmt(&mut self, node: &SwitchStmt) {
+        node.discriminant.visit_with(self);
+        let mut bindings = Vec::new();
+        for case in &node.cases {
+            stmt_bindings(&case.cons, &mut bindings);
+        }
+        self.with_scope(bindings, |this| node.cases.visit_with(this));
+    }
+
+    fn visit_function(&mut self, node: &Function) {
+        let mut bindings = Vec::new();
+        for param in &node.params {
+            pat_bindings(&param.pat, &mut bindings);
+        }
+        if let Some(body) = &node.body {
+            bindings.extend(VarCollector::collect(body));
+        }
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_constructor(&mut self, node: &Constructor) {
+        let mut bindings = Vec::new();
+        for param in &node.params {
+            match param {
+                ParamOrTsParamProp::Param(param) => pat_bindings(&param.pat, &mut bindings),
+                ParamOrTsParamProp::TsParamProp(prop) => match &prop.param {
+                    TsParamPropParam::Ident(ident) => bindings.push(Binding::declared(&ident.id)),
+                    TsParamPropParam::Assign(assign) => pat_bindings(&assign.left, &mut bindings),
+                },
+            }
+        }
+        if let Some(body) = &node.body {
+            bindings.extend(VarCollector::collect(body));
+        }
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_arrow_expr(&mut self, node: &ArrowExpr) {
+        let mut bindings = Vec::new();
+        for param in &node.params {
+            pat_bindings(param, &mut bindings);
+        }
+        if let BlockStmtOrExpr::BlockStmt(body) = &*node.body {
+            bindings.extend(VarCollector::collect(body));
+        }
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    /// The name of `function name() {}` used as an expression is only visible inside it
+    fn visit_fn_expr(&mut self, node: &FnExpr) {
+        let bindings = node.ident.iter().map(Binding::declared).collect();
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_class_expr(&mut self, node: &ClassExpr) {
+        let bindings = node.ident.iter().map(Binding::declared).collect();
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_catch_clause(&mut self, node: &CatchClause) {
+        let mut bindings = Vec::new();
+        if let Some(param) = &node.param {
+            pat_bindings(param, &mut bindings);
+        }
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_for_stmt(&mut self, node: &ForStmt) {
+        let mut bindings = Vec::new();
+        if let Some(VarDeclOrExpr::VarDecl(var)) = &node.init {
+            if var.kind != VarDeclKind::Var {
+                var.decls
+                    .iter()
+                    .for_each(|declarator| pat_bindings(&declarator.name, &mut bindings));
+            }
+        }
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_for_in_stmt(&mut self, node: &ForInStmt) {
+        let bindings = for_head_bindings(&node.left);
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+
+    fn visit_for_of_stmt(&mut self, node: &ForOfStmt) {
+        let bindings = for_head_bindings(&node.left);
+        self.with_scope(bindings, |this| node.visit_children_with(this));
+    }
+}
+
+fn for_head_bindings(head: &ForHead) -> Vec<Binding> {
+    let mut bindings = Vec::new();
+    if let ForHead::VarDecl(var) = head {
+        if var.kind != VarDeclKind::Var {
+            var.decls
+                .iter()
+                .for_each(|declarator| pat_bindings(&declarator.name, &mut bindings));
+        }
+    }
+    bindings
+}
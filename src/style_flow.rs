@@ -0,0 +1,671 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    process,
+};
+
+use anyhow::Result;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use swc_ecma_parser::Syntax;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    cache::Cache,
+    config::{LinterConfig, Properties},
+    parsers::{
+        extract_css_imports, extract_script_summary, extract_used_classes, ArgSlot, Binding,
+        BindingSource, CssImports, ExportBinding, FunctionSummary, ParamBinding, ParsedScript,
+        ScriptDialect, ScriptSummary, StylesRoots, UsedClassName, ValueRef,
+    },
+    utils::{process_relative_import, replace_aliases},
+};
+
+/// Same-file calls of imported functions can chain through re-exports, this bounds the chain
+const MAX_RESOLVE_DEPTH: usize = 16;
+
+/// Analysis of a script kept in the cache
+#[derive(Serialize, Deserialize)]
+pub struct ScriptAnalysis {
+    pub css_imports: CssImports,
+    pub summary: ScriptSummary,
+    /// Usages found the last time the file was searched, `None` until it is
+    usages: Option<FlowUsages>,
+}
+
+impl ScriptAnalysis {
+    pub fn new(script: &ParsedScript) -> Self {
+        ScriptAnalysis {
+            css_imports: extract_css_imports(script),
+            summary: extract_script_summary(&script.module, &script.scopes),
+            usages: None,
+        }
+    }
+}
+
+/// Usages of the style modules held by a particular set of references
+#[derive(Serialize, Deserialize)]
+struct FlowUsages {
+    /// Hash of the references, see `roots_key`
+    roots: u64,
+    used: HashMap<String, HashSet<UsedClassName>>,
+}
+
+/// A script with its analysis and the imports of the analysis resolved to files of the project
+struct ScriptFile {
+    /// Path as listed by the project walk
+    entry: String,
+    code: String,
+    syntax: Syntax,
+    /// Parsed only when the analysis is missing from the cache or the usages are outdated,
+    /// dropped once they are found
+    script: Option<ParsedScript>,
+    analysis: ScriptAnalysis,
+    /// Whether the cache holds `analysis` as it is
+    is_stored: bool,
+    /// Whether `analysis` may be stored, not for unsaved editor text
+    is_cacheable: bool,
+    /// `(style path, variable)` for each import of a whole style module
+    style_objects: Vec<(String, Binding)>,
+    named_usages: Vec<(String, UsedClassName)>,
+    /// Imports resolved to a script of the project by local name, with the script and imported name
+    imports: HashMap<String, (Binding, String, String)>,
+    /// Style modules re-exported as a whole by exported name
+    style_exports: HashMap<String, String>,
+    /// `export { a as b } from` by exported name, with the script and imported name
+    reexports: HashMap<String, (String, String)>,
+    /// Scripts of `export * from`
+    star_reexports: Vec<String>,
+}
+
+/// Where an exported name of a script is defined
+enum ExportTarget<'a> {
+    /// A binding of the script `.0`
+    Local(&'a str, &'a ExportBinding),
+    /// A style module re-exported as a whole
+    Style(&'a str),
+}
+
+fn resolve_path(requester: &str, specifier: &str, tsconfig: &Properties) -> Result<String> {
+    let mut path = specifier.to_string();
+    process_relative_import(Path::new(requester), &mut path)?;
+    replace_aliases(&mut path, &tsconfig.compiler_options.paths);
+    Ok(path)
+}
+
+/// Finds the script an extensionless import specifier points to
+fn resolve_script(path: &str, scripts: &HashSet<String>) -> Option<String> {
+    let bases = [
+        path.to_string(),
+        format!("./{}", path.trim_start_matches("./")),
+    ];
+
+    bases.iter().find_map(|base| {
+        let candidates = std::iter::once(base.clone())
+            .chain(
+                ScriptDialect::EXTENSIONS
+                    .iter()
+                    .map(|ext| format!("{}.{}", base, ext)),
+            )
+            .chain(
+                ScriptDialect::EXTENSIONS
+                    .iter()
+                    .map(|ext| format!("{}/index.{}", base, ext)),
+            );
+        candidates.into_iter().find(|c| scripts.contains(c))
+    })
+}
+
+/// Style modules each reference of each file may hold
+#[derive(Default)]
+struct FlowState {
+    roots: HashMap<String, HashMap<ValueRef, HashSet<String>>>,
+    followed: HashMap<String, HashSet<u32>>,
+}
+
+impl FlowState {
+    fn get(&self, file: &str, value: &ValueRef) -> HashSet<String> {
+        self.roots
+            .get(file)
+            .and_then(|roots| roots.get(value))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn add(&mut self, file: &str, value: ValueRef, styles: &HashSet<String>) -> bool {
+        let entry = self
+            .roots
+            .entry(file.to_string())
+            .or_default()
+            .entry(value)
+            .or_default();
+        let len = entry.len();
+        entry.extend(styles.iter().cloned());
+        entry.len() != len
+    }
+
+    fn follow(&mut self, file: &str, pos: u32) {
+        self.followed
+            .entry(file.to_string())
+            .or_default()
+            .insert(pos);
+    }
+}
+
+/// Follows `name` exported by `file` through re-exports and barrel files to its definition
+fn resolve_export<'a>(
+    files: &'a HashMap<String, ScriptFile>,
+    file: &'a str,
+    name: &str,
+    visited: &mut HashSet<(String, String)>,
+) -> Option<ExportTarget<'a>> {
+    if !visited.insert((file.to_string(), name.to_string())) {
+        return None;
+    }
+
+    let script = files.get(file)?;
+    if let Some(export) = script.analysis.summary.exports.get(name) {
+        return Some(ExportTarget::Local(file, export));
+    }
+    if let Some(style_path) = script.style_exports.get(name) {
+        return Some(ExportTarget::Style(style_path));
+    }
+    if let Some((target, imported)) = script.reexports.get(name) {
+        return resolve_export(files, target, imported, visited);
+    }
+    // `export *` never re-exports the default export
+    if name == "default" {
+        return None;
+    }
+    script
+        .star_reexports
+        .iter()
+        .find_map(|target| resolve_export(files, target, name, visited))
+}
+
+/// Finds the function `binding` of a file refers to, following imports into other scripts
+fn resolve_function<'a>(
+    files: &'a HashMap<String, ScriptFile>,
+    file: &'a str,
+    binding: &Binding,
+    depth: usize,
+) -> Option<(&'a str, &'a FunctionSummary)> {
+    let script = files.get(file)?;
+    if let Some(function) = script.analysis.summary.function(binding) {
+        return Some((file, function));
+    }
+    if depth > MAX_RESOLVE_DEPTH {
+        return None;
+    }
+
+    let (_, target, imported) = script
+        .imports
+        .get(&binding.name)
+        .filter(|(local, _, _)| local == binding)?;
+    match resolve_export(files, target, imported, &mut HashSet::new())? {
+        ExportTarget::Local(file, export) => {
+            resolve_function(files, file, &export.local, depth + 1)
+        }
+        ExportTarget::Style(_) => None,
+    }
+}
+
+/// Reference a parameter of `function` gets when `slot` is passed to it
+fn param_ref(function: &FunctionSummary, slot: &ArgSlot) -> Option<ValueRef> {
+    match (slot, function.params.first()) {
+        (ArgSlot::Index(index), _) => match function.params.get(*index)? {
+            ParamBinding::Ident(name) => Some(ValueRef::Ident(name.clone())),
+            _ => None,
+        },
+        // `function Card(props)` reads `props.styles`, `function Card({ styles: s })` reads `s`
+        (ArgSlot::Prop(prop), Some(ParamBinding::Ident(props))) => {
+            Some(ValueRef::Member(props.clone(), prop.clone()))
+        }
+        (ArgSlot::Prop(prop), Some(ParamBinding::Object(props))) => props
+            .iter()
+            .find(|(key, _)| key == prop)
+            .map(|(_, local)| ValueRef::Ident(local.clone())),
+        _ => None,
+    }
+}
+
+/// Propagates the imported style modules through bindings, props, arguments,
+/// return values and exports until nothing changes
+fn propagate(files: &HashMap<String, ScriptFile>) -> FlowState {
+    let mut state = FlowState::default();
+    for (key, file) in files {
+        for (style_path, variable) in &file.style_objects {
+            let styles = HashSet::from([style_path.clone()]);
+            state.add(key, ValueRef::Ident(variable.clone()), &styles);
+        }
+    }
+
+    loop {
+        let mut changed = false;
+
+        for (key, file) in files {
+            for (name, source) in &file.analysis.summary.bindings {
+                match source {
+                    BindingSource::Value(value) => {
+                        let styles = state.get(key, &value.value);
+                        if !styles.is_empty() {
+                            changed |= state.add(key, ValueRef::Ident(name.clone()), &styles);
+                            state.follow(key, value.pos);
+                        }
+                    }
+                    BindingSource::Call(callee) => {
+                        let Some((target, function)) = resolve_function(files, key, callee, 0)
+                        else {
+                            continue;
+                        };
+                        for returned in &function.returns {
+                            let styles = state.get(target, &returned.value);
+                            if !styles.is_empty() {
+                                changed |= state.add(key, ValueRef::Ident(name.clone()), &styles);
+                                state.follow(target, returned.pos);
+                            }
+                        }
+                    }
+                }
+            }
+
+            for pass in &file.analysis.summary.passes {
+                let styles = state.get(key, &pass.value.value);
+                if styles.is_empty() {
+                    continue;
+                }
+                let Some((target, function)) = resolve_function(files, key, &pass.callee, 0) else {
+                    continue;
+                };
+                if let Some(param) = param_ref(function, &pass.slot) {
+                    changed |= state.add(target, param, &styles);
+                    state.follow(key, pass.value.pos);
+                }
+            }
+
+            for (local, target, imported) in file.imports.values() {
+                let (styles, followed) =
+                    match resolve_export(files, target, imported, &mut HashSet::new()) {
+                        Some(ExportTarget::Local(file, export)) => {
+                            let value = ValueRef::Ident(export.local.clone());
+                            (state.get(file, &value), export.pos.map(|pos| (file, pos)))
+                        }
+                        Some(ExportTarget::Style(style_path)) => {
+                            (HashSet::from([style_path.to_string()]), None)
+                        }
+                        None => continue,
+                    };
+                if styles.is_empty() {
+                    continue;
+                }
+                changed |= state.add(key, ValueRef::Ident(local.clone()), &styles);
+                if let Some((file, pos)) = followed {
+                    state.follow(file, pos);
+                }
+            }
+        }
+
+        if !changed {
+            return state;
+        }
+    }
+}
+
+fn parse_error(entry: &str, e: anyhow::Error) -> ! {
+    eprintln!("Could not parse file: {}\n{}", entry, e);
+    process::exit(1);
+}
+
+/// Reads and analyzes a single script, the inner error is a syntax error in it
+fn load_script(
+    entry: &str,
+    path: &str,
+    syntax: Syntax,
+    cache: &Cache,
+) -> Result<Result<ScriptFile>> {
+    let code = fs::read_to_string(entry)?;
+    Ok(analyze_script(entry, path, code, syntax, cache))
+}
+
+/// Analyzes the script at `entry` holding `code`, failing on a syntax error
+fn analyze_script(
+    entry: &str,
+    path: &str,
+    code: String,
+    syntax: Syntax,
+    cache: &Cache,
+) -> Result<ScriptFile> {
+    let (script, analysis) = match cache.get::<ScriptAnalysis>(path, &code) {
+        Some(analysis) => (None, analysis),
+        None => match ParsedScript::parse(&code, path.to_string(), syntax) {
+            Ok(script) => {
+                let analysis = ScriptAnalysis::new(&script);
+                (Some(script), analysis)
+            }
+            Err(e) => return Err(e),
+        },
+    };
+
+    Ok(ScriptFile {
+        entry: entry.to_string(),
+        code,
+        syntax,
+        is_stored: script.is_none(),
+        is_cacheable: cache.is_enabled(),
+        script,
+        analysis,
+        style_objects: Vec::new(),
+        named_usages: Vec::new(),
+        imports: HashMap::new(),
+        style_exports: HashMap::new(),
+        reexports: HashMap::new(),
+        star_reexports: Vec::new(),
+    })
+}
+
+impl ScriptFile {
+    /// Resolves the style modules and scripts the file imports and re-exports
+    fn resolve_links(&mut self, tsconfig: &Properties, scripts: &HashSet<String>) -> Result<()> {
+        let entry = self.entry.as_str();
+        let css_imports = &self.analysis.css_imports;
+        let summary = &self.analysis.summary;
+
+        self.style_objects.clear();
+        for (style_path, variable) in &css_imports.objects {
+            let style_path = resolve_path(entry, style_path, tsconfig)?;
+            self.style_objects.push((style_path, variable.clone()));
+        }
+        self.named_usages.clear();
+        for (style_path, usage) in &css_imports.named {
+            let style_path = resolve_path(entry, style_path, tsconfig)?;
+            self.named_usages.push((style_path, usage.clone()));
+        }
+        self.style_exports.clear();
+        for (style_path, exported) in &css_imports.reexports {
+            let style_path = resolve_path(entry, style_path, tsconfig)?;
+            self.style_exports.insert(exported.clone(), style_path);
+        }
+        let resolve_target = |specifier: &str| -> Result<Option<String>> {
+            let resolved = resolve_path(entry, specifier, tsconfig)?;
+            Ok(resolve_script(&resolved, scripts))
+        };
+        self.imports.clear();
+        for (name, import) in &summary.imports {
+            if let Some(target) = resolve_target(&import.specifier)? {
+                let resolved_import = (import.local.clone(), target, import.imported.clone());
+                self.imports.insert(name.clone(), resolved_import);
+            }
+        }
+        self.reexports.clear();
+        for (exported, (specifier, imported)) in &summary.reexports {
+            if let Some(target) = resolve_target(specifier)? {
+                self.reexports
+                    .insert(exported.clone(), (target, imported.clone()));
+            }
+        }
+        self.star_reexports.clear();
+        for specifier in &summary.star_reexports {
+            self.star_reexports.extend(resolve_target(specifier)?);
+        }
+        Ok(())
+    }
+
+    /// Finds the usages of the style modules held by `roots` again, unless the file
+    /// is unchanged and the same references hold the same style modules as last time
+    fn update_usages(
+        &mut self,
+        path: &str,
+        roots: &StylesRoots,
+        config: &LinterConfig,
+    ) -> Result<()> {
+        let key = roots_key(roots);
+        let script = self.script.take();
+        if matches!(&self.analysis.usages, Some(usages) if usages.roots == key) {
+            return Ok(());
+        }
+
+        let mut used = HashMap::new();
+        if !roots.values.is_empty() {
+            let script = match script {
+                Some(script) => script,
+                None => {
+                    ParsedScript::parse(&self.code, path.to_string(), self.syntax).map_err(|e| {
+                        anyhow::Error::msg(format!("Could not parse file: {}\n{}", path, e))
+                    })?
+                }
+            };
+            used = extract_used_classes(&script, roots, &config.class_helpers);
+        }
+        self.analysis.usages = Some(FlowUsages { roots: key, used });
+        self.is_stored = false;
+        Ok(())
+    }
+}
+
+/// Hash identifying the references a file holds style modules in, cached usages
+/// of the file are reused while the flow into it stays the same
+fn roots_key(roots: &StylesRoots) -> u64 {
+    let mut values: Vec<String> = roots
+        .values
+        .iter()
+        .map(|(value, styles)| {
+            let mut styles: Vec<&String> = styles.iter().collect();
+            styles.sort();
+            format!("{:?}{:?}", value, styles)
+        })
+        .collect();
+    values.sort();
+    let mut followed: Vec<&u32> = roots.followed.iter().collect();
+    followed.sort();
+    xxh3_64(format!("{:?}{:?}", values, followed).as_bytes())
+}
+
+/// The scripts of a project with their analysis, kept between lint runs of watch mode
+/// so that only changed files are read again
+#[derive(Default)]
+pub struct ScriptFiles {
+    /// Scripts by path with forward slashes
+    files: HashMap<String, ScriptFile>,
+}
+
+impl ScriptFiles {
+    /// Reads and analyzes the scripts among `entries`, a syntax error in any of them ends the process
+    pub fn load(entries: &[String], config: &LinterConfig, cache: &Cache) -> Result<Self> {
+        let (files, errors) = Self::load_valid(entries, config, cache)?;
+        if let Some((entry, e)) = errors.into_iter().next() {
+            parse_error(&entry, e);
+        }
+        Ok(files)
+    }
+
+    /// Reads and analyzes the scripts among `entries`, leaving out the ones with syntax errors.
+    /// The errors are returned in listing order, so every run blames the same file first.
+    pub fn load_valid(
+        entries: &[String],
+        config: &LinterConfig,
+        cache: &Cache,
+    ) -> Result<(Self, Vec<(String, anyhow::Error)>)> {
+        let script_entries: Vec<(&String, String, Syntax)> = entries
+            .iter()
+            .filter_map(|entry| {
+                let path = entry.replace("\\", "/");
+                let dialect = ScriptDialect::from_path(&path)?;
+                Some((entry, path, dialect.syntax(config.decorators)))
+            })
+            .collect();
+
+        let loaded: Vec<_> = script_entries
+            .par_iter()
+            .map(|(entry, path, syntax)| load_script(entry, path, *syntax, cache))
+            .collect();
+        let mut files = HashMap::new();
+        let mut errors = Vec::new();
+        for ((entry, path, _), file) in script_entries.iter().zip(loaded) {
+            match file? {
+                Ok(file) => {
+                    files.insert(path.clone(), file);
+                }
+                Err(e) => errors.push((entry.to_string(), e)),
+            }
+        }
+        Ok((ScriptFiles { files }, errors))
+    }
+
+    /// Analyzes the script at `entry` again with `code` as its content,
+    /// on a syntax error its previous analysis is kept
+    pub fn update(
+        &mut self,
+        entry: &str,
+        code: String,
+        config: &LinterConfig,
+        cache: &Cache,
+    ) -> Result<()> {
+        let path = entry.replace("\\", "/");
+        let Some(dialect) = ScriptDialect::from_path(&path) else {
+            return Ok(());
+        };
+
+        let file = analyze_script(entry, &path, code, dialect.syntax(config.decorators), cache)?;
+        self.files.insert(path, file);
+        Ok(())
+    }
+
+    /// Style modules imported as a whole by the script at `path`, with the bindings holding them
+    pub fn style_objects(&self, path: &str) -> &[(String, Binding)] {
+        self.files
+            .get(path)
+            .map_or(&[], |file| file.style_objects.as_slice())
+    }
+
+    /// Forgets the script at `entry` and every script under it when it is a directory
+    pub fn remove(&mut self, entry: &str) {
+        let path = entry.replace("\\", "/");
+        let dir = format!("{}/", path);
+        self.files
+            .retain(|file, _| file != &path && !file.starts_with(&dir));
+    }
+
+    /// Usages of every style module by the scripts, keyed by style module path.
+    /// Styles objects are followed through props, arguments, return values and exports across files.
+    /// Only files whose flow changed since the last call are searched for usages again.
+    pub fn used_classes(
+        &mut self,
+        tsconfig: &Properties,
+        config: &LinterConfig,
+        cache: &Cache,
+    ) -> Result<HashMap<String, HashSet<UsedClassName>>> {
+        let scripts: HashSet<String> = self.files.keys().cloned().collect();
+        for file in self.files.values_mut() {
+            file.resolve_links(tsconfig, &scripts)?;
+        }
+
+        let mut state = propagate(&self.files);
+        let roots: HashMap<String, StylesRoots> = self
+            .files
+            .keys()
+            .map(|path| {
+                let roots = StylesRoots {
+                    values: state.roots.remove(path).unwrap_or_default(),
+                    followed: state.followed.remove(path).unwrap_or_default(),
+                };
+                (path.clone(), roots)
+            })
+            .collect();
+
+        self.files.par_iter_mut().try_for_each(|(path, file)| {
+            file.update_usages(path, &roots[path], config)?;
+            if file.is_cacheable && !file.is_stored {
+                cache.put(path, &file.code, &file.analysis);
+                file.is_stored = true;
+            }
+            Ok::<_, anyhow::Error>(())
+        })?;
+
+        let mut used_classnames: HashMap<String, HashSet<UsedClassName>> = HashMap::new();
+        for file in self.files.values() {
+            let usages = file.analysis.usages.iter().flat_map(|usages| &usages.used);
+            for (style_path, used) in usages {
+                let used_fields = used_classnames.entry(style_path.clone()).or_default();
+                used_fields.extend(used.iter().cloned());
+            }
+            for (style_path, usage) in &file.named_usages {
+                let used_fields = used_classnames.entry(style_path.clone()).or_default();
+                used_fields.insert(usage.clone());
+            }
+        }
+
+        Ok(used_classnames)
+    }
+}
+
+/// Usages of every style module by the scripts among `entries`, keyed by style module path
+pub fn collect_used_classes(
+    entries: &[String],
+    tsconfig: &Properties,
+    config: &LinterConfig,
+    cache: &Cache,
+) -> Result<HashMap<String, HashSet<UsedClassName>>> {
+    ScriptFiles::load(entries, config, cache)?.used_classes(tsconfig, config, cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn load_valid_leaves_out_scripts_that_fail_to_parse() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, code: &str| {
+            let entry = dir.path().join(name).to_string_lossy().into_owned();
+            fs::write(&entry, code).unwrap();
+            entry
+        };
+        let card = write(
+            "card.tsx",
+            "import styles from './card.module.css';\nexport const Card = () => <div className={styles.card} />;",
+        );
+        let broken = write("broken.tsx", "export const Broken = () => <div>;");
+        let title = write(
+            "title.tsx",
+            "import styles from './title.module.css';\nexport const Title = () => <h1 className={styles.title} />;",
+        );
+        let also_broken = write("also_broken.ts", "const = 1;");
+        let config = LinterConfig {
+            cache: false,
+            ..Default::default()
+        };
+        let cache = Cache::open(&config);
+
+        let entries = [
+            card.clone(),
+            broken.clone(),
+            title.clone(),
+            also_broken.clone(),
+        ];
+        let (mut files, errors) = ScriptFiles::load_valid(&entries, &config, &cache).unwrap();
+
+        let failed: Vec<&String> = errors.iter().map(|(entry, _)| entry).collect();
+        assert_eq!(failed, [&broken, &also_broken]);
+        let mut loaded: Vec<&String> = files.files.keys().collect();
+        loaded.sort();
+        assert_eq!(
+            loaded,
+            [&card.replace("\\", "/"), &title.replace("\\", "/")]
+        );
+
+        let tsconfig: Properties =
+            serde_json::from_str(r#"{ "compilerOptions": { "paths": {} }, "exclude": [] }"#)
+                .unwrap();
+        let used = files.used_classes(&tsconfig, &config, &cache).unwrap();
+        let mut used: Vec<&str> = used
+            .values()
+            .flatten()
+            .map(|class| class.class_name.as_str())
+            .collect();
+        used.sort();
+        assert_eq!(used, ["card", "title"]);
+    }
+}
@@ -5,8 +5,9 @@ use std::{
 };
 
 use anyhow::Result;
+use rayon::prelude::*;
 
-pub fn replace_aliases(s: &mut String, aliases: HashMap<String, Vec<String>>) {
+pub fn replace_aliases(s: &mut String, aliases: &HashMap<String, Vec<String>>) {
     for (from, to) in aliases {
         if to.is_empty() {
             continue;
@@ -34,7 +35,7 @@ pub fn process_relative_import(requester_path: &Path, destination_path: &mut Str
 
     if destination_path.starts_with(".") {
         if let Some(parent) = requester_path.parent() {
-            let normalized = normalize_path(&parent, orig_path);
+            let normalized = normalize_path(parent, orig_path);
             *destination_path = normalized.to_string_lossy().into_owned().replace("\\", "/");
         }
     }
@@ -42,30 +43,32 @@ pub fn process_relative_import(requester_path: &Path, destination_path: &mut Str
     Ok(())
 }
 
-pub fn list_files_in_directory(path: PathBuf, exclude: Vec<String>) -> Vec<String> {
-    let mut files = Vec::new();
-
-    if let Ok(entries) = fs::read_dir(&path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
+/// Files of the tree under `path`, subdirectories are walked in parallel
+/// and listed in the order they are read
+pub fn list_files_in_directory(path: PathBuf, exclude: &[String]) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(&path) else {
+        eprintln!("Cannot open target dir: {:?}", path);
+        return Vec::new();
+    };
 
+    let paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths
+        .into_par_iter()
+        .map(|path| {
             if path.is_dir() {
                 if let Some(p) = path.file_name() {
                     let p_str = p.to_string_lossy();
                     if p_str.starts_with('.') || exclude.iter().any(|i| p_str == *i) {
-                        continue;
+                        return Vec::new();
                     }
                 }
-                files.extend(list_files_in_directory(path, exclude.clone()));
+                list_files_in_directory(path, exclude)
             } else if path.is_file() {
-                if let Some(path_str) = path.to_str() {
-                    files.push(path_str.to_string());
-                }
+                path.to_str().map(str::to_string).into_iter().collect()
+            } else {
+                Vec::new()
             }
-        }
-    } else {
-        eprintln!("Cannot open target dir: {:?}", path);
-    }
-
-    files
+        })
+        .flatten()
+        .collect()
 }
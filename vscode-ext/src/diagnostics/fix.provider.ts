@@ -5,11 +5,11 @@ export class CssFixProvider implements vscode.CodeActionProvider {
 
     provideCodeActions(document: vscode.TextDocument, range: vscode.Range): vscode.CodeAction[] {
         const actions: vscode.CodeAction[] = [];
-        const disable_rule = '/* css-lint-disable-rule unused-class*/';
-
         const diagnostics = this.diagnostics.get(document.uri) || [];
         for (const diagnostic of diagnostics) {
-            if (diagnostic.range.intersection(range)) {
+            const rule = disabledRule(diagnostic.message);
+            if (rule && diagnostic.range.intersection(range)) {
+                const disable_rule = `/* css-lint-disable-rule ${rule}*/`;
                 const fix = new vscode.CodeAction(
                     `Add ${disable_rule}`,
                     vscode.CodeActionKind.QuickFix
@@ -31,3 +31,10 @@ export class CssFixProvider implements vscode.CodeActionProvider {
         return actions;
     }
 }
+
+const disabledRule = (message: string): string | undefined => {
+    if (message.includes('Unused class')) return 'unused-class';
+    if (message.includes('Unused export')) return 'unused-export';
+    if (message.includes('Unused value')) return 'unused-value';
+    return undefined;
+};
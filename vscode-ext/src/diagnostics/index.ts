@@ -19,18 +19,25 @@ export const run_diag = async (diagnosticCollection: vscode.DiagnosticCollection
     const diagnosticsMap: Map<string, vscode.Diagnostic[]> = new Map();
     for (let e_line of error_lines) {
         let frags = e_line.split(':');
-        if (frags.length < 4) {
+        if (frags.length < 7) {
             continue;
         }
 
         const filePath = frags[0];
         const line = parseInt(frags[1]) - 1;
         const col = parseInt(frags[2]);
-        const len = parseInt(frags[3]);
-        const message = frags.slice(4).join(':');
-
-        const range = new vscode.Range(line, col, line, col + len);
-        const diagnostic = new vscode.Diagnostic(range, message, vscode.DiagnosticSeverity.Warning);
+        const endLine = parseInt(frags[3]) - 1;
+        const endCol = parseInt(frags[4]);
+        const severity =
+            frags[5] === 'error'
+                ? vscode.DiagnosticSeverity.Error
+                : frags[5] === 'info'
+                ? vscode.DiagnosticSeverity.Information
+                : vscode.DiagnosticSeverity.Warning;
+        const message = frags.slice(6).join(':');
+
+        const range = new vscode.Range(line, col, endLine, endCol);
+        const diagnostic = new vscode.Diagnostic(range, message, severity);
         diagnostic.source = 'next-css-linter';
 
         const diagnostics = diagnosticsMap.get(filePath) || [];
@@ -40,23 +40,23 @@ export function activate(context: vscode.ExtensionContext) {
         code_action_type
     );
     let definition_provider = vscode.languages.registerDefinitionProvider(
-        fileFilter,
+        fileFilterAll,
         css_definition
     );
     let completion_provider = vscode.languages.registerCompletionItemProvider(
-        fileFilter,
+        fileFilterAll,
         css_completion,
         '.'
     );
 
-    let hover_provider = vscode.languages.registerHoverProvider(fileFilter, css_hover);
+    let hover_provider = vscode.languages.registerHoverProvider(fileFilterAll, css_hover);
     let extractor = vscode.commands.registerCommand('next-css-lint.convert-inline', convert_css);
     let css_reference_provider = vscode.languages.registerReferenceProvider('css', css_references);
 
     const enable_command = vscode.commands.registerCommand('next-css-lint.enable', async () => {
         await config.update('enabled', true, vscode.ConfigurationTarget.Workspace);
         save_evt = vscode.workspace.onDidSaveTextDocument(() => run_diag(diagnosticCollection));
-        hover_provider = vscode.languages.registerHoverProvider(fileFilter, css_hover);
+        hover_provider = vscode.languages.registerHoverProvider(fileFilterAll, css_hover);
         extractor = vscode.commands.registerCommand('next-css-lint.convert-inline', convert_css);
         css_reference_provider = vscode.languages.registerReferenceProvider('css', css_references);
         code_action = vscode.languages.registerCodeActionsProvider(
@@ -65,11 +65,11 @@ export function activate(context: vscode.ExtensionContext) {
             code_action_type
         );
         definition_provider = vscode.languages.registerDefinitionProvider(
-            fileFilter,
+            fileFilterAll,
             css_definition
         );
         completion_provider = vscode.languages.registerCompletionItemProvider(
-            fileFilter,
+            fileFilterAll,
             css_completion,
             '.'
         );
@@ -37,9 +37,10 @@ export class CSSReferenceProvider implements vscode.ReferenceProvider {
                 }
                 const file = vscode.Uri.file(path);
                 const line = parseInt(usage[1]) - 1;
-                const col = parseInt(usage[2]) + 1;
-                const len = parseInt(usage[3]);
-                return new vscode.Location(file, new vscode.Range(line, col, line, col + len));
+                const col = parseInt(usage[2]);
+                const endLine = parseInt(usage[3]) - 1;
+                const endCol = parseInt(usage[4]);
+                return new vscode.Location(file, new vscode.Range(line, col, endLine, endCol));
             });
     }
 }